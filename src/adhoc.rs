@@ -0,0 +1,290 @@
+//! Ad-hoc submission of URLs given on the command line, in a file or on stdin
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::config;
+use crate::db::{self, Source, SourceHost, UrlStatus};
+use crate::hosts;
+use crate::keyfile;
+use crate::output::{self, say, Record};
+use crate::submit::{self, SubmitEntry, SubmitReason};
+use colored::*;
+use rusqlite::Connection;
+use std::io::{BufRead, IsTerminal};
+use std::path::Path;
+use url::Url;
+
+/// What to submit and where, as given with `--submit`
+#[derive(Debug, Default)]
+pub struct AdhocArgs {
+    /// URLs given as arguments
+    pub urls: Vec<String>,
+    /// File with one URL per line, `-` for stdin
+    pub file: Option<String>,
+    /// Source whose hosts, keys and engines are used
+    pub source: Option<i64>,
+    pub key: Option<String>,
+    pub host: Option<String>,
+    pub key_location: Option<String>,
+    /// Engines to submit to instead of the source's or the default
+    pub endpoints: Option<Vec<String>>,
+    /// Track the URLs under the source, as if it had listed them
+    pub record: bool,
+    pub dry_run: bool,
+}
+
+/// Where ad-hoc URLs are submitted
+struct Target {
+    source: Option<Source>,
+    hosts: Vec<SourceHost>,
+    endpoints: Vec<String>,
+}
+
+/// Submit the given URLs right away to every engine of the target. Nothing
+/// is queued: a rejected URL is reported and, with `record`, retried on the
+/// source's next run.
+pub fn run(db_path: &Path, args: &AdhocArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = db::init_db(db_path)?;
+    let source = find_source(&conn, args)?;
+    let urls = read_urls(args, source.as_ref())?;
+    let target = resolve_target(args, source, &urls)?;
+
+    let off_host: Vec<&str> = urls
+        .iter()
+        .map(String::as_str)
+        .filter(|url| !hosts::is_allowed(url, &target.hosts))
+        .collect();
+    if !off_host.is_empty() {
+        let names: Vec<&str> = target.hosts.iter().map(|h| h.host.as_str()).collect();
+        return Err(format!(
+            "{} URL(s) are not on {}: {}",
+            off_host.len(),
+            names.join(", "),
+            off_host.join(", ")
+        )
+        .into());
+    }
+
+    if let Some(source) = &target.source {
+        if !keyfile::preflight(&conn, source, args.dry_run)? && !args.dry_run {
+            return Err(format!("Key file check failed for source {}", source.id).into());
+        }
+    }
+
+    let entries: Vec<SubmitEntry> = urls
+        .into_iter()
+        .map(|url| SubmitEntry {
+            url,
+            reason: SubmitReason::Forced,
+        })
+        .collect();
+    let source_id = target.source.as_ref().map(|s| s.id);
+    if let Some(id) = source_id {
+        let endpoints: Vec<&str> = target.endpoints.iter().map(String::as_str).collect();
+        for entry in &entries {
+            output::emit(&Record::url(id, entry, endpoints.clone(), args.dry_run));
+        }
+    }
+
+    if args.dry_run {
+        say!(
+            "{} Would submit {} URL(s) to {}{}:",
+            "ℹ".cyan().bold(),
+            entries.len(),
+            target.endpoints.join(", "),
+            if args.record { " and record them" } else { "" }
+        );
+        for entry in &entries {
+            say!("  • {}", entry.url);
+        }
+        return Ok(());
+    }
+
+    let urls: Vec<&str> = entries.iter().map(|e| e.url.as_str()).collect();
+    if let (Some(id), true) = (source_id, args.record) {
+        db::track_urls(&conn, id, &urls)?;
+        db::record_pending_endpoints(&conn, id, &urls, &target.endpoints)?;
+    }
+
+    let mut failed: Vec<&str> = Vec::new();
+    for endpoint in &target.endpoints {
+        say!(
+            "\n{} Submitting {} URL(s) to {}...\n",
+            "→".blue().bold(),
+            entries.len(),
+            endpoint
+        );
+        let result = submit::submit_in_batches(&target.hosts, endpoint, &entries, |report| {
+            db::log_submission(&conn, source_id, report)?;
+            output::emit(&Record::Batch {
+                source_id,
+                endpoint: report.endpoint,
+                host: report.host,
+                urls: report.entries.iter().map(|e| e.url.as_str()).collect(),
+                status: report.status,
+                accepted: report.accepted(),
+                duration_ms: report.duration.as_millis(),
+                response_body: &report.response_body,
+            });
+            if let (Some(id), true) = (source_id, args.record) {
+                let urls: Vec<&str> = report.entries.iter().map(|e| e.url.as_str()).collect();
+                if report.accepted() {
+                    db::mark_urls_submitted(&conn, id, report.endpoint, &urls)?;
+                } else {
+                    db::set_urls_status(&conn, id, report.endpoint, &urls, UrlStatus::Failed)?;
+                }
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => say!("\n{} {} accepted {} URL(s).", "✓".green().bold(), endpoint, entries.len()),
+            Err(e) => {
+                say!("\n{} {}: {}", "✗".red().bold(), endpoint, e);
+                failed.push(endpoint);
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        if args.record {
+            say!(
+                "{} Rejected URLs are retried on the next run of source {}.",
+                "ℹ".cyan().bold(),
+                source_id.unwrap_or_default()
+            );
+        }
+        return Err(format!("Submission failed on {}", failed.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// The source given with `--source`, checking that it is not combined with
+/// settings of its own
+fn find_source(conn: &Connection, args: &AdhocArgs) -> Result<Option<Source>, Box<dyn std::error::Error>> {
+    let Some(id) = args.source else {
+        if args.record {
+            return Err("--record needs --source to know which source to record the URLs under".into());
+        }
+        return Ok(None);
+    };
+    if args.key.is_some() || args.host.is_some() || args.key_location.is_some() {
+        return Err("--key, --host and --key-location can't be combined with --source; the source's settings are used".into());
+    }
+    let source = db::get_all_sources(conn)?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("No source with ID {}. Run '{} --list' to see available sources.", id, env!("CARGO_PKG_NAME")))?;
+    Ok(Some(source))
+}
+
+/// Use the source's hosts and engines, or build a one-off host from the flags.
+/// The host defaults to the host of the first URL.
+fn resolve_target(args: &AdhocArgs, source: Option<Source>, urls: &[String]) -> Result<Target, Box<dyn std::error::Error>> {
+    if let Some(source) = source {
+        return Ok(Target {
+            hosts: source.hosts(),
+            endpoints: args.endpoints.clone().unwrap_or_else(|| source.endpoints.clone()),
+            source: Some(source),
+        });
+    }
+
+    let key = args
+        .key
+        .as_deref()
+        .ok_or("--key or --source is required to submit URLs")?;
+    keyfile::validate_key(key).map_err(|e| format!("--key: {}", e))?;
+    let host = match &args.host {
+        Some(host) => host.clone(),
+        None => urls.first().and_then(|url| hosts::url_host(url)).unwrap_or_default(),
+    };
+    let host = hosts::extra_host(&host, key, args.key_location.as_deref())?;
+    Ok(Target {
+        source: None,
+        hosts: vec![host],
+        endpoints: args
+            .endpoints
+            .clone()
+            .unwrap_or_else(|| vec![config::DEFAULT_ENDPOINT.to_string()]),
+    })
+}
+
+/// URLs from the arguments, the file and stdin, in order and without
+/// duplicates. Blank lines and lines starting with `#` are skipped. A
+/// source's normalization is applied so recorded URLs match its own.
+fn read_urls(args: &AdhocArgs, source: Option<&Source>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut lines: Vec<String> = args.urls.clone();
+    match args.file.as_deref() {
+        Some("-") => lines.extend(read_lines(std::io::stdin().lock())?),
+        Some(path) => {
+            let file = std::fs::File::open(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            lines.extend(read_lines(std::io::BufReader::new(file))?);
+        }
+        None if args.urls.is_empty() && !std::io::stdin().is_terminal() => {
+            lines.extend(read_lines(std::io::stdin().lock())?);
+        }
+        None => {}
+    }
+
+    let mut urls: Vec<String> = Vec::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match Url::parse(line) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => return Err(format!("Not an http(s) URL: {}", line).into()),
+        }
+        let url = match source {
+            Some(source) => source.normalization.apply(line),
+            None => line.to_string(),
+        };
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+
+    if urls.is_empty() {
+        return Err("No URLs to submit: give them as arguments, with --file, or on stdin".into());
+    }
+    Ok(urls)
+}
+
+fn read_lines(reader: impl BufRead) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    Ok(reader.lines().collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_urls() {
+        let args = AdhocArgs {
+            urls: vec![
+                " https://example.com/a ".to_string(),
+                "# skipped".to_string(),
+                "https://example.com/b".to_string(),
+                "https://example.com/a".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(read_urls(&args, None).unwrap(), vec!["https://example.com/a", "https://example.com/b"]);
+
+        let args = AdhocArgs { urls: vec!["example.com/a".to_string()], ..Default::default() };
+        assert!(read_urls(&args, None).unwrap_err().to_string().contains("Not an http(s) URL"));
+    }
+}
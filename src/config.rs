@@ -1,25 +1,33 @@
 //! Configuration management for ixfeed
 
-/// Copyright (C) 2026 Andre Franca <andre@abf.li>
-///
-/// This program is free software: you can redistribute it and/or modify
-/// it under the terms of the GNU Affero General Public License as published by
-/// the Free Software Foundation, either version 3 of the License, or
-/// (at your option) any later version.
-///
-/// This program is distributed in the hope that it will be useful,
-/// but WITHOUT ANY WARRANTY; without even the implied warranty of
-/// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
-/// GNU Affero General Public License for more details.
-///
-/// You should have received a copy of the GNU Affero General Public License
-/// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db;
+use crate::feed;
+use crate::hosts;
+use crate::keyfile;
+use crate::output::{self, say, HostRecord, Record};
+use crate::queue;
+use crate::watch;
 use colored::*;
 use dialoguer::{Confirm, Input, Select};
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, Response};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::time::Duration;
 use url::Url;
 
@@ -28,6 +36,61 @@ pub enum SourceType {
     #[default]
     Feed,
     Sitemap,
+    /// A host's robots.txt; every `Sitemap:` directive in it is walked
+    Robots,
+}
+
+impl SourceType {
+    pub const ALL: [SourceType; 3] = [SourceType::Feed, SourceType::Sitemap, SourceType::Robots];
+
+    /// Value stored in the `source_type` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceType::Feed => "feed",
+            SourceType::Sitemap => "sitemap",
+            SourceType::Robots => "robots",
+        }
+    }
+
+    /// Parse a stored `source_type`; unknown values are treated as feeds
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "sitemap" => SourceType::Sitemap,
+            "robots" => SourceType::Robots,
+            _ => SourceType::Feed,
+        }
+    }
+
+    /// Name used in progress messages ("Fetching feed from ...")
+    pub fn noun(&self) -> &'static str {
+        match self {
+            SourceType::Feed => "feed",
+            SourceType::Sitemap => "sitemap",
+            SourceType::Robots => "robots.txt",
+        }
+    }
+
+    /// Short label used in source lists
+    pub fn label(&self) -> &'static str {
+        match self {
+            SourceType::Feed => "Feed",
+            SourceType::Sitemap => "Sitemap",
+            SourceType::Robots => "Robots",
+        }
+    }
+}
+
+impl std::str::FromStr for SourceType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "feed" => Ok(SourceType::Feed),
+            "sitemap" => Ok(SourceType::Sitemap),
+            "robots" | "robots.txt" => Ok(SourceType::Robots),
+            other => Err(format!("expected 'feed', 'sitemap' or 'robots', got '{}'", other)),
+        }
+    }
 }
 
 impl std::fmt::Display for SourceType {
@@ -35,6 +98,7 @@ impl std::fmt::Display for SourceType {
         match self {
             SourceType::Feed => write!(f, "RSS/Atom/JSON Feed"),
             SourceType::Sitemap => write!(f, "Sitemap XML"),
+            SourceType::Robots => write!(f, "robots.txt (sitemap discovery)"),
         }
     }
 }
@@ -42,18 +106,86 @@ impl std::fmt::Display for SourceType {
 // Re-export Source from db module for convenience
 pub use crate::db::Source;
 
+/// IndexNow endpoint used when none is given
+pub const DEFAULT_ENDPOINT: &str = "api.indexnow.org";
+
+/// Parse a comma-separated list of IndexNow endpoints (search engine hosts).
+/// Schemes and trailing slashes are stripped and duplicates removed.
+pub fn parse_endpoints(input: &str) -> Vec<String> {
+    let mut endpoints: Vec<String> = Vec::new();
+    for part in input.split(',') {
+        let endpoint = part
+            .trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_end_matches('/')
+            .to_ascii_lowercase();
+        if !endpoint.is_empty() && !endpoints.contains(&endpoint) {
+            endpoints.push(endpoint);
+        }
+    }
+    endpoints
+}
+
+fn print_available_endpoints() {
+    say!("\n{}", "Available IndexNow endpoints (comma-separate to submit to several):".dimmed());
+    say!("  • api.indexnow.org (recommended, forwards to all)");
+    say!("  • www.bing.com");
+    say!("  • yandex.com");
+    say!("  • search.seznam.cz");
+    say!("  • searchadvisor.naver.com\n");
+}
+
 /// Extract host (domain) from a URL
 fn extract_host_from_url(url: &str) -> Option<String> {
     Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_string()))
 }
 
-/// Validate source URL: must be valid format, HTTPS (auto-upgrade from HTTP), and accessible
-/// Returns the validated (possibly upgraded) URL on success
-fn validate_source_url(url: &str, source_type: SourceType) -> Result<String, String> {
+/// Validate source URL: must be valid format, HTTPS (auto-upgrade from HTTP), and accessible.
+/// For feeds, an HTML page is replaced by a feed it advertises (chosen by the user when
+/// `interactive`, otherwise the first one).
+/// Returns the validated (possibly upgraded or discovered) URL on success
+pub fn validate_source_url(url: &str, source_type: SourceType, interactive: bool) -> Result<String, String> {
+    let final_url = normalize_source_url(url, source_type)?;
+    
+    // Check if accessible
+    say!("  {} Validating URL...", "→".blue());
+    
+    let client = Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent(format!(
+            "{}/{}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        ))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    
+    let response = probe_url(&client, &final_url, source_type)?;
+
+    // A web page given as a feed: look for the feeds it advertises
+    if source_type == SourceType::Feed && is_html(&response) {
+        let html = response
+            .text()
+            .map_err(|e| format!("Could not read page: {}", e))?;
+        let feed_url = choose_discovered_feed(&feed::discover_feeds(&html, &final_url), interactive)?;
+        let response = probe_url(&client, &feed_url, source_type)?;
+        if is_html(&response) {
+            return Err(format!("{} is an HTML page, not a feed.", feed_url));
+        }
+        return Ok(feed_url);
+    }
+    
+    Ok(final_url)
+}
+
+/// Check a source URL's format without fetching it: adds a missing scheme,
+/// upgrades HTTP to HTTPS and points bare robots.txt hosts at `/robots.txt`
+pub fn normalize_source_url(url: &str, source_type: SourceType) -> Result<String, String> {
     // Auto-add https:// if no scheme is present
     let url_with_scheme = if !url.contains("://") {
         let fixed = format!("https://{}", url);
-        println!(
+        say!(
             "  {} Added HTTPS prefix: {}",
             "↑".cyan(),
             fixed
@@ -69,7 +201,7 @@ fn validate_source_url(url: &str, source_type: SourceType) -> Result<String, Str
     // Auto-upgrade HTTP to HTTPS
     if parsed.scheme() == "http" {
         parsed.set_scheme("https").map_err(|_| "Failed to upgrade to HTTPS")?;
-        println!(
+        say!(
             "  {} Auto-upgraded to HTTPS: {}",
             "↑".cyan(),
             parsed.as_str()
@@ -82,24 +214,25 @@ fn validate_source_url(url: &str, source_type: SourceType) -> Result<String, Str
     if parsed.host_str().is_none() {
         return Err("URL must have a valid host".to_string());
     }
+
+    // A bare host is taken to mean its robots.txt
+    if source_type == SourceType::Robots && parsed.path() == "/" {
+        parsed.set_path("/robots.txt");
+        say!(
+            "  {} Using robots.txt: {}",
+            "→".cyan(),
+            parsed.as_str()
+        );
+    }
     
-    let final_url = parsed.to_string();
-    
-    // Check if accessible
-    println!("  {} Validating URL...", "→".blue());
-    
-    let client = Client::builder()
-        .timeout(Duration::from_secs(15))
-        .user_agent(format!(
-            "{}/{}",
-            env!("CARGO_PKG_NAME"),
-            env!("CARGO_PKG_VERSION")
-        ))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-    
+    Ok(parsed.to_string())
+}
+
+/// Fetch a source URL, failing on non-success statuses and warning about
+/// unexpected content types
+fn probe_url(client: &Client, url: &str, source_type: SourceType) -> Result<Response, String> {
     let response = client
-        .get(&final_url)
+        .get(url)
         .send()
         .map_err(|e| {
             format!(
@@ -126,23 +259,76 @@ fn validate_source_url(url: &str, source_type: SourceType) -> Result<String, Str
                 // application/xml, text/xml, application/json, etc.
             }
             SourceType::Sitemap => {
-                if !ct.contains("xml") && !ct.contains("text/plain") {
-                    println!(
+                if !ct.contains("xml") && !ct.contains("text/plain") && !ct.contains("gzip") {
+                    say!(
                         "  {} Content-Type is '{}', expected XML. Proceeding anyway.",
                         "⚠".yellow(),
                         ct
                     );
                 }
             }
+            SourceType::Robots => {
+                if !ct.contains("text/plain") {
+                    say!(
+                        "  {} Content-Type is '{}', expected text/plain. Proceeding anyway.",
+                        "⚠".yellow(),
+                        ct
+                    );
+                }
+            }
         }
     }
-    
-    Ok(final_url)
+
+    Ok(response)
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .headers()
+        .get("content-type")
+        .and_then(|ct| ct.to_str().ok())
+        .is_some_and(|ct| ct.contains("text/html"))
+}
+
+/// Pick one of the feeds advertised by an HTML page: the user chooses when
+/// interactive, otherwise the first one is used
+fn choose_discovered_feed(feeds: &[feed::DiscoveredFeed], interactive: bool) -> Result<String, String> {
+    if feeds.is_empty() {
+        return Err(
+            "URL is an HTML page that does not advertise any feed.\n    Please enter the feed URL directly."
+                .to_string(),
+        );
+    }
+
+    let index = if interactive && feeds.len() > 1 {
+        say!(
+            "  {} The page advertises {} feeds.",
+            "ℹ".cyan(),
+            feeds.len()
+        );
+        let labels: Vec<String> = feeds.iter().map(|f| f.to_string()).collect();
+        Select::new()
+            .with_prompt("Select the feed to use")
+            .items(&labels)
+            .default(0)
+            .interact()
+            .map_err(|e| e.to_string())?
+    } else {
+        0
+    };
+
+    let chosen = &feeds[index];
+    say!(
+        "  {} Using feed discovered on the page: {}",
+        "→".cyan(),
+        chosen.url
+    );
+    Ok(chosen.url.clone())
 }
 
 /// Check if there are any sources configured
-pub fn has_sources() -> bool {
-    match db::init_db() {
+pub fn has_sources(db_path: &Path) -> bool {
+    match db::init_db(db_path) {
         Ok(conn) => {
             let sources = db::get_all_sources(&conn).unwrap_or_default();
             !sources.is_empty()
@@ -152,40 +338,35 @@ pub fn has_sources() -> bool {
 }
 
 /// Get all configured sources
-pub fn get_sources() -> Result<Vec<Source>, Box<dyn std::error::Error>> {
-    let conn = db::init_db()?;
+pub fn get_sources(db_path: &Path) -> Result<Vec<Source>, Box<dyn std::error::Error>> {
+    let conn = db::init_db(db_path)?;
     Ok(db::get_all_sources(&conn)?)
 }
 
 /// Add a new source (feed or sitemap) with per-source config
-pub fn add_source(source_type: SourceType, source_url: &str, api_key: &str, host: &str, searchengine: &str) -> Result<i64, Box<dyn std::error::Error>> {
-    let conn = db::init_db()?;
+pub fn add_source(db_path: &Path, source_type: SourceType, source_url: &str, api_key: &str, host: &str, endpoints: &[String]) -> Result<i64, Box<dyn std::error::Error>> {
+    let conn = db::init_db(db_path)?;
     
     // Check if source already exists
     if db::source_exists(&conn, source_url)? {
         return Err(format!("Source already exists: {}", source_url).into());
     }
     
-    let type_str = match source_type {
-        SourceType::Feed => "feed",
-        SourceType::Sitemap => "sitemap",
-    };
-    
-    let id = db::add_source(&conn, type_str, source_url, api_key, host, searchengine)?;
+    let id = db::add_source(&conn, source_type.as_str(), source_url, api_key, host, endpoints)?;
     Ok(id)
 }
 
 /// Remove a source by ID
-pub fn remove_source(id: i64) -> Result<bool, Box<dyn std::error::Error>> {
-    let conn = db::init_db()?;
+pub fn remove_source(db_path: &Path, id: i64) -> Result<bool, Box<dyn std::error::Error>> {
+    let conn = db::init_db(db_path)?;
     Ok(db::remove_source(&conn, id)?)
 }
 
-pub fn edit_config() -> Result<(), Box<dyn std::error::Error>> {
-    let sources = get_sources()?;
+pub fn edit_config(db_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = get_sources(db_path)?;
 
     if sources.is_empty() {
-        println!(
+        say!(
             "{} No sources configured. Run '{} --add' to add a source first.",
             "⚠".yellow().bold(),
             env!("CARGO_PKG_NAME")
@@ -193,20 +374,17 @@ pub fn edit_config() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    println!(
+    say!(
         "{} Edit Source Configuration",
         "═".repeat(40).blue().bold()
     );
 
     // List available sources
-    println!("\n{}", "Available sources:".bold());
+    say!("\n{}", "Available sources:".bold());
     let source_labels: Vec<String> = sources
         .iter()
         .map(|s| {
-            let type_str = match s.source_type.as_str() {
-                "sitemap" => "Sitemap",
-                _ => "Feed",
-            };
+            let type_str = SourceType::from_db(&s.source_type).label();
             format!("[ID {}] {} - {}", s.id, type_str, s.source_url)
         })
         .collect();
@@ -218,17 +396,18 @@ pub fn edit_config() -> Result<(), Box<dyn std::error::Error>> {
 
     let source = &sources[selection];
 
-    println!("\n{}", "Edit settings (press Enter to keep current value):".dimmed());
+    say!("\n{}", "Edit settings (press Enter to keep current value):".dimmed());
 
     // Source Type
-    let type_options = vec!["RSS/Atom/JSON Feed", "Sitemap XML"];
-    let current_type_idx = if source.source_type == "sitemap" { 1 } else { 0 };
+    let type_options: Vec<String> = SourceType::ALL.iter().map(|t| t.to_string()).collect();
+    let current_type = SourceType::from_db(&source.source_type);
+    let current_type_idx = SourceType::ALL.iter().position(|t| *t == current_type).unwrap_or(0);
     let type_selection = Select::new()
         .with_prompt(format!("Source Type [{}]", type_options[current_type_idx]))
         .items(&type_options)
         .default(current_type_idx)
         .interact()?;
-    let new_source_type = if type_selection == 1 { "sitemap" } else { "feed" };
+    let new_source_type = SourceType::ALL[type_selection];
 
     // Source URL
     let new_url: String = Input::new()
@@ -239,12 +418,11 @@ pub fn edit_config() -> Result<(), Box<dyn std::error::Error>> {
         source.source_url.clone()
     } else {
         // Validate the new URL if changed
-        let stype = if new_source_type == "sitemap" { SourceType::Sitemap } else { SourceType::Feed };
-        match validate_source_url(&new_url, stype) {
+        match validate_source_url(&new_url, new_source_type, true) {
             Ok(validated) => validated,
             Err(e) => {
-                println!("{} {}", "✗".red().bold(), e);
-                println!("Keeping original URL.");
+                say!("{} {}", "✗".red().bold(), e);
+                say!("Keeping original URL.");
                 source.source_url.clone()
             }
         }
@@ -260,10 +438,16 @@ pub fn edit_config() -> Result<(), Box<dyn std::error::Error>> {
         .with_prompt(format!("API Key [{}]", current_key))
         .allow_empty(true)
         .interact_text()?;
-    let new_api_key = if new_api_key.is_empty() {
-        source.api_key.clone()
-    } else {
-        new_api_key
+    let new_api_key = match new_api_key.trim() {
+        "" => source.api_key.clone(),
+        key => match keyfile::validate_key(key) {
+            Ok(()) => key.to_string(),
+            Err(e) => {
+                say!("{} Invalid key: {}", "✗".red().bold(), e);
+                say!("Keeping original API key.");
+                source.api_key.clone()
+            }
+        },
     };
 
     // Host
@@ -282,83 +466,95 @@ pub fn edit_config() -> Result<(), Box<dyn std::error::Error>> {
         new_host
     };
 
-    // Search Engine
-    let current_engine = if source.searchengine.is_empty() {
-        "api.indexnow.org".to_string()
+    // Key Location
+    let new_key_location: String = Input::new()
+        .with_prompt(format!(
+            "Key Location, 'none' for the host root [{}]",
+            source.key_location.as_deref().unwrap_or("host root")
+        ))
+        .allow_empty(true)
+        .interact_text()?;
+    let new_key_location = match new_key_location.trim() {
+        "" => source.key_location.clone(),
+        "none" => None,
+        location => match hosts::validate_key_location(location, &new_host) {
+            Ok(()) => Some(location.to_string()),
+            Err(e) => {
+                say!("{} {}", "✗".red().bold(), e);
+                say!("Keeping current key location.");
+                source.key_location.clone()
+            }
+        },
+    };
+
+    // Search Engines
+    let current_endpoints = if source.endpoints.is_empty() {
+        vec![DEFAULT_ENDPOINT.to_string()]
     } else {
-        source.searchengine.clone()
+        source.endpoints.clone()
     };
-    println!("\n{}", "Available IndexNow endpoints:".dimmed());
-    println!("  • api.indexnow.org (recommended, forwards to all)");
-    println!("  • www.bing.com");
-    println!("  • yandex.com");
-    println!("  • search.seznam.cz\n");
-
-    let new_searchengine: String = Input::new()
-        .with_prompt(format!("Search Engine Host [{}]", current_engine))
+    print_available_endpoints();
+
+    let new_endpoints: String = Input::new()
+        .with_prompt(format!("Search Engine Hosts [{}]", current_endpoints.join(", ")))
         .allow_empty(true)
         .interact_text()?;
-    let new_searchengine = if new_searchengine.is_empty() {
-        if source.searchengine.is_empty() {
-            "api.indexnow.org".to_string()
-        } else {
-            source.searchengine.clone()
-        }
-    } else {
-        new_searchengine
+    let new_endpoints = match parse_endpoints(&new_endpoints) {
+        endpoints if endpoints.is_empty() => current_endpoints,
+        endpoints => endpoints,
     };
 
     // Summary and confirm
-    println!("\n{}", "Updated Configuration:".bold());
-    println!("  Type:          {}", if new_source_type == "sitemap" { "Sitemap".cyan() } else { "Feed".cyan() });
-    println!("  URL:           {}", new_url.green());
-    println!("  API Key:       {}", mask_key(&new_api_key));
-    println!("  Host:          {}", new_host.green());
-    println!("  Search Engine: {}", new_searchengine.green());
+    say!("\n{}", "Updated Configuration:".bold());
+    say!("  Type:          {}", new_source_type.label().cyan());
+    say!("  URL:           {}", new_url.green());
+    say!("  API Key:       {}", mask_key(&new_api_key));
+    say!("  Host:          {}", new_host.green());
+    say!("  Key Location:  {}", new_key_location.as_deref().unwrap_or("(host root)").green());
+    say!("  Search Engines: {}", new_endpoints.join(", ").green());
 
     if Confirm::new()
         .with_prompt("Save changes?")
         .default(true)
         .interact()?
     {
-        let conn = db::init_db()?;
-        db::update_source(&conn, source.id, new_source_type, &new_url, &new_api_key, &new_host, &new_searchengine)?;
-        println!(
+        let conn = db::init_db(db_path)?;
+        db::update_source(&conn, source.id, new_source_type.as_str(), &new_url, &new_api_key, &new_host, &new_endpoints)?;
+        db::set_source_key_location(&conn, source.id, new_key_location.as_deref())?;
+        say!(
             "{} Configuration saved.",
             "✓".green().bold()
         );
     } else {
-        println!("{} Configuration not saved.", "⚠".yellow().bold());
+        say!("{} Configuration not saved.", "⚠".yellow().bold());
     }
 
     Ok(())
 }
 
 /// Interactive source addition
-pub fn add_source_interactive() -> Result<(), Box<dyn std::error::Error>> {
-    println!(
-        "{} Add New Source (Feed or Sitemap)",
+pub fn add_source_interactive(db_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    say!(
+        "{} Add New Source (Feed, Sitemap or robots.txt)",
         "═".repeat(35).blue().bold()
     );
 
     // Source Type Selection
-    println!("\n{}", "URL Source Type:".bold());
-    let source_options = vec!["RSS/Atom/JSON Feed", "Sitemap XML"];
+    say!("\n{}", "URL Source Type:".bold());
+    let source_options: Vec<String> = SourceType::ALL.iter().map(|t| t.to_string()).collect();
     let selection = Select::new()
         .with_prompt("Select source type")
         .items(&source_options)
         .default(0)
         .interact()?;
     
-    let source_type = match selection {
-        0 => SourceType::Feed,
-        _ => SourceType::Sitemap,
-    };
+    let source_type = SourceType::ALL[selection];
 
     // Source URL (required, validated)
     let source_label = match source_type {
         SourceType::Feed => "RSS/Atom/JSON Feed URL",
         SourceType::Sitemap => "Sitemap URL (e.g., https://example.com/sitemap.xml)",
+        SourceType::Robots => "robots.txt URL (e.g., https://example.com/robots.txt)",
     };
     
     let validated_url = loop {
@@ -367,25 +563,25 @@ pub fn add_source_interactive() -> Result<(), Box<dyn std::error::Error>> {
             .interact_text()?;
         
         if source_url.is_empty() {
-            println!("{} Source URL is required.", "⚠".yellow().bold());
+            say!("{} Source URL is required.", "⚠".yellow().bold());
             continue;
         }
         
         // Check if already exists
-        let conn = db::init_db()?;
+        let conn = db::init_db(db_path)?;
         if db::source_exists(&conn, &source_url)? {
-            println!("{} This source already exists.", "⚠".yellow().bold());
+            say!("{} This source already exists.", "⚠".yellow().bold());
             continue;
         }
         
         // Validate the URL
-        match validate_source_url(&source_url, source_type) {
+        match validate_source_url(&source_url, source_type, true) {
             Ok(validated_url) => {
-                println!("  {} URL is valid and accessible.", "✓".green().bold());
+                say!("  {} URL is valid and accessible.", "✓".green().bold());
                 break validated_url;
             }
             Err(e) => {
-                println!("{} {}", "✗".red().bold(), e);
+                say!("{} {}", "✗".red().bold(), e);
                 continue;
             }
         }
@@ -394,17 +590,22 @@ pub fn add_source_interactive() -> Result<(), Box<dyn std::error::Error>> {
     // Extract host suggestion from URL
     let suggested_host = extract_host_from_url(&validated_url).unwrap_or_default();
 
-    println!("\n{}", "IndexNow API Settings for this source:".bold());
+    say!("\n{}", "IndexNow API Settings for this source:".bold());
     
     // API Key (required)
     let api_key = loop {
         let key: String = Input::new()
-            .with_prompt("API Key (your IndexNow key)")
+            .with_prompt(format!("API Key (your IndexNow key, '{} --keygen' makes one)", env!("CARGO_PKG_NAME")))
             .interact_text()?;
-        if !key.is_empty() {
-            break key;
+        let key = key.trim();
+        if key.is_empty() {
+            say!("{} API Key is required.", "⚠".yellow().bold());
+            continue;
+        }
+        match keyfile::validate_key(key) {
+            Ok(()) => break key.to_string(),
+            Err(e) => say!("{} Invalid key: {}", "✗".red().bold(), e),
         }
-        println!("{} API Key is required.", "⚠".yellow().bold());
     };
 
     // Host (required)
@@ -418,64 +619,66 @@ pub fn add_source_interactive() -> Result<(), Box<dyn std::error::Error>> {
         } else if !suggested_host.is_empty() {
             break suggested_host.clone();
         }
-        println!("{} Host is required.", "⚠".yellow().bold());
+        say!("{} Host is required.", "⚠".yellow().bold());
     };
 
-    // Search Engine
-    println!("\n{}", "Available IndexNow endpoints:".dimmed());
-    println!("  • api.indexnow.org (recommended, forwards to all)");
-    println!("  • www.bing.com");
-    println!("  • yandex.com");
-    println!("  • search.seznam.cz\n");
+    // Search Engines
+    print_available_endpoints();
 
-    let searchengine: String = Input::new()
-        .with_prompt("Search Engine Host [api.indexnow.org]")
+    let endpoints: String = Input::new()
+        .with_prompt(format!("Search Engine Hosts [{}]", DEFAULT_ENDPOINT))
         .allow_empty(true)
         .interact_text()?;
-    let searchengine = if searchengine.is_empty() {
-        "api.indexnow.org".to_string()
-    } else {
-        searchengine
+    let endpoints = match parse_endpoints(&endpoints) {
+        endpoints if endpoints.is_empty() => vec![DEFAULT_ENDPOINT.to_string()],
+        endpoints => endpoints,
     };
 
     // Summary and confirm
-    println!("\n{}", "Source Summary:".bold());
-    println!("  Type:          {}", source_type.to_string().cyan());
-    println!("  URL:           {}", validated_url.green());
-    println!("  API Key:       {}", mask_key(&api_key));
-    println!("  Host:          {}", host.green());
-    println!("  Search Engine: {}", searchengine.green());
+    say!("\n{}", "Source Summary:".bold());
+    say!("  Type:          {}", source_type.to_string().cyan());
+    say!("  URL:           {}", validated_url.green());
+    say!("  API Key:       {}", mask_key(&api_key));
+    say!("  Host:          {}", host.green());
+    say!("  Search Engines: {}", endpoints.join(", ").green());
 
     if Confirm::new()
         .with_prompt("Add this source?")
         .default(true)
         .interact()?
     {
-        let id = add_source(source_type, &validated_url, &api_key, &host, &searchengine)?;
+        let id = add_source(db_path, source_type, &validated_url, &api_key, &host, &endpoints)?;
         
-        println!(
+        say!(
             "\n{} Source added successfully (ID: {})",
             "✓".green().bold(),
             id
         );
     } else {
-        println!("{} Operation cancelled.", "ℹ".cyan().bold());
+        say!("{} Operation cancelled.", "ℹ".cyan().bold());
     }
 
     Ok(())
 }
 
 /// List all configured sources
-pub fn list_sources() -> Result<(), Box<dyn std::error::Error>> {
-    let sources = get_sources()?;
+pub fn list_sources(db_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = get_sources(db_path)?;
+
+    if output::is_structured() {
+        for source in &sources {
+            output::emit(&source_record(source, None));
+        }
+        return Ok(());
+    }
     
-    println!(
+    say!(
         "{} Configured Sources",
         "═".repeat(40).blue().bold()
     );
     
     if sources.is_empty() {
-        println!(
+        say!(
             "\n{} No sources configured. Run '{} --add' to add a source.",
             "⚠".yellow().bold(),
             env!("CARGO_PKG_NAME")
@@ -483,32 +686,30 @@ pub fn list_sources() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
     
-    println!();
+    say!();
     for source in &sources {
-        let type_str = match source.source_type.as_str() {
-            "sitemap" => "Sitemap".cyan(),
-            _ => "Feed".cyan(),
-        };
+        let type_str = SourceType::from_db(&source.source_type).label().cyan();
         let status = if source.first_run_completed {
             "synced".green()
         } else {
             "new".yellow()
         };
-        println!(
+        say!(
             "  ID {} [{}] {} ({})",
             source.id.to_string().bold(),
             type_str,
             source.source_url,
             status
         );
-        println!("     API Key: {}  Host: {}  Engine: {}",
+        say!("     API Key: {}  Host: {}  Engines: {}  Interval: {}",
             mask_key(&source.api_key),
             if source.host.is_empty() { "(not set)".red().to_string() } else { source.host.green().to_string() },
-            source.searchengine.dimmed()
+            source.endpoints.join(", ").dimmed(),
+            poll_interval_label(source).dimmed()
         );
     }
     
-    println!(
+    say!(
         "\n{} Use '{} -e <ids>' to process specific sources.",
         "ℹ".cyan().bold(),
         env!("CARGO_PKG_NAME")
@@ -518,30 +719,27 @@ pub fn list_sources() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 /// Remove a source interactively
-pub fn remove_source_interactive() -> Result<(), Box<dyn std::error::Error>> {
-    let sources = get_sources()?;
+pub fn remove_source_interactive(db_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = get_sources(db_path)?;
     
     if sources.is_empty() {
-        println!(
+        say!(
             "{} No sources configured.",
             "ℹ".cyan().bold()
         );
         return Ok(());
     }
     
-    println!(
+    say!(
         "{} Remove Source",
         "═".repeat(40).blue().bold()
     );
     
     // List sources
-    println!("\n{}", "Available sources:".bold());
+    say!("\n{}", "Available sources:".bold());
     for source in &sources {
-        let type_str = match source.source_type.as_str() {
-            "sitemap" => "Sitemap",
-            _ => "Feed",
-        };
-        println!("  ID {} [{}] {}", source.id, type_str, source.source_url);
+        let type_str = SourceType::from_db(&source.source_type).label();
+        say!("  ID {} [{}] {}", source.id, type_str, source.source_url);
     }
     
     // Ask for ID
@@ -551,7 +749,7 @@ pub fn remove_source_interactive() -> Result<(), Box<dyn std::error::Error>> {
         .interact_text()?;
     
     if id.is_empty() {
-        println!("{} Operation cancelled.", "ℹ".cyan().bold());
+        say!("{} Operation cancelled.", "ℹ".cyan().bold());
         return Ok(());
     }
     
@@ -565,39 +763,50 @@ pub fn remove_source_interactive() -> Result<(), Box<dyn std::error::Error>> {
     let source = source.unwrap();
     
     // Confirm
-    println!("\n{} This will remove:", "⚠ WARNING:".yellow().bold());
-    println!("  Source: {}", source.source_url);
-    println!("  And all associated submitted URLs from the database.\n");
+    say!("\n{} This will remove:", "⚠ WARNING:".yellow().bold());
+    say!("  Source: {}", source.source_url);
+    say!("  And all associated submitted URLs from the database.\n");
     
     if Confirm::new()
         .with_prompt("Are you sure?")
         .default(false)
         .interact()?
     {
-        remove_source(id)?;
-        println!(
+        remove_source(db_path, id)?;
+        say!(
             "{} Source removed.",
             "✓".green().bold()
         );
     } else {
-        println!("{} Operation cancelled.", "ℹ".cyan().bold());
+        say!("{} Operation cancelled.", "ℹ".cyan().bold());
     }
 
     Ok(())
 }
 
-pub fn list_config() -> Result<(), Box<dyn std::error::Error>> {
-    let sources = get_sources()?;
+pub fn list_config(db_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let sources = get_sources(db_path)?;
 
-    println!(
+    if output::is_structured() {
+        let conn = db::init_db(db_path)?;
+        for source in &sources {
+            let mut states: BTreeMap<String, BTreeMap<&'static str, i64>> = BTreeMap::new();
+            for (endpoint, status, count) in db::get_endpoint_stats(&conn, source.id)? {
+                states.entry(endpoint).or_default().insert(status.as_str(), count);
+            }
+            output::emit(&source_record(source, Some(states)));
+        }
+        return Ok(());
+    }
+
+    say!(
         "{} IndexNow Configuration",
         "═".repeat(40).blue().bold()
     );
-    let db_path = db::db_path().map(|p| p.display().to_string()).unwrap_or_else(|_| "unknown".to_string());
-    println!("Stored in: SQLite database at {}\n", db_path.dimmed());
+    say!("Stored in: SQLite database at {}\n", db_path.display().to_string().dimmed());
 
     if sources.is_empty() {
-        println!(
+        say!(
             "{} No sources configured. Run '{} --add' to add a source.",
             "⚠".yellow().bold(),
             env!("CARGO_PKG_NAME")
@@ -605,30 +814,28 @@ pub fn list_config() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    println!("{} ({}):", "Sources".bold(), sources.len());
+    let conn = db::init_db(db_path)?;
+    say!("{} ({}):", "Sources".bold(), sources.len());
     for source in &sources {
-        let type_str = match source.source_type.as_str() {
-            "sitemap" => "Sitemap".cyan(),
-            _ => "Feed".cyan(),
-        };
+        let type_str = SourceType::from_db(&source.source_type).label().cyan();
         let status = if source.first_run_completed {
             "synced".green()
         } else {
             "new".yellow()
         };
-        println!(
+        say!(
             "\n  ID {} [{}] {} ({})",
             source.id.to_string().bold(),
             type_str,
             source.source_url.green(),
             status
         );
-        println!(
+        say!(
             "     {} {}",
             "API Key:".bold(),
             mask_key(&source.api_key)
         );
-        println!(
+        say!(
             "     {} {}",
             "Host:".bold(),
             if source.host.is_empty() {
@@ -637,15 +844,87 @@ pub fn list_config() -> Result<(), Box<dyn std::error::Error>> {
                 source.host.green().to_string()
             }
         );
-        println!(
+        say!(
             "     {} {}",
-            "Search Engine:".bold(),
-            if source.searchengine.is_empty() {
+            "Key Location:".bold(),
+            match &source.key_location {
+                Some(location) => location.green().to_string(),
+                None => "(host root)".dimmed().to_string(),
+            }
+        );
+        for extra in &source.extra_hosts {
+            say!(
+                "     {} {} (key {}{})",
+                "Extra Host:".bold(),
+                extra.host.green(),
+                mask_key(&extra.api_key),
+                extra
+                    .key_location
+                    .as_deref()
+                    .map(|l| format!(", key file {}", l))
+                    .unwrap_or_default()
+            );
+        }
+        say!(
+            "     {} {}",
+            "Search Engines:".bold(),
+            if source.endpoints.is_empty() {
                 "(not set)".red().to_string()
             } else {
-                source.searchengine.green().to_string()
+                source.endpoints.join(", ").green().to_string()
             }
         );
+
+        say!(
+            "     {} {}",
+            "Poll Interval:".bold(),
+            poll_interval_label(source)
+        );
+        say!(
+            "     {} {}",
+            "Removals:".bold(),
+            removals_label(source)
+        );
+        say!(
+            "     {} {}",
+            "URL Normalization:".bold(),
+            source.normalization
+        );
+        if !source.filters.is_empty() {
+            say!("     {}", "Filters:".bold());
+            for rule in &source.filters {
+                say!("       {}", rule.to_string().dimmed());
+            }
+        }
+
+        // Per-endpoint submission state
+        let stats = db::get_endpoint_stats(&conn, source.id)?;
+        for endpoint in &source.endpoints {
+            let counts: Vec<String> = stats
+                .iter()
+                .filter(|(e, _, _)| e == endpoint)
+                .map(|(_, status, count)| format!("{} {}", count, status.as_str()))
+                .collect();
+            if !counts.is_empty() {
+                say!("       {} {}", format!("{}:", endpoint).dimmed(), counts.join(", ").dimmed());
+            }
+        }
+        let quarantined = db::get_urls_with_status(&conn, source.id, db::UrlStatus::OffHost)?.len();
+        if quarantined > 0 {
+            say!(
+                "       {} {}",
+                "Off-host:".dimmed(),
+                format!("{} quarantined", quarantined).yellow()
+            );
+        }
+        let removed = db::get_urls_with_status(&conn, source.id, db::UrlStatus::Removed)?.len();
+        if removed > 0 {
+            say!(
+                "       {} {}",
+                "Removed:".dimmed(),
+                format!("{} submitted as removed", removed).dimmed()
+            );
+        }
     }
 
     Ok(())
@@ -654,11 +933,71 @@ pub fn list_config() -> Result<(), Box<dyn std::error::Error>> {
 fn mask_key(key: &str) -> String {
     if key.is_empty() {
         "(not set)".red().to_string()
-    } else if key.len() <= 8 {
-        format!("{}***", &key[..2]).green().to_string()
     } else {
-        format!("{}...{}", &key[..4], &key[key.len() - 4..])
-            .green()
-            .to_string()
+        redact_key(key).green().to_string()
+    }
+}
+
+/// Show only the start and end of a key
+fn redact_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 8 {
+        format!("{}***", chars.iter().take(2).collect::<String>())
+    } else {
+        format!(
+            "{}...{}",
+            chars[..4].iter().collect::<String>(),
+            chars[chars.len() - 4..].iter().collect::<String>()
+        )
+    }
+}
+
+fn source_record<'a>(
+    source: &'a Source,
+    url_states: Option<BTreeMap<String, BTreeMap<&'static str, i64>>>,
+) -> Record<'a> {
+    Record::Source {
+        id: source.id,
+        source_type: SourceType::from_db(&source.source_type).as_str(),
+        url: &source.source_url,
+        host: &source.host,
+        key: redact_key(&source.api_key),
+        key_location: source.key_location.as_deref(),
+        endpoints: &source.endpoints,
+        first_run_completed: source.first_run_completed,
+        poll_interval: source.poll_interval,
+        removal_grace: source.removal_grace,
+        removal_probe: source.removal_probe,
+        filters: &source.filters,
+        extra_hosts: source
+            .extra_hosts
+            .iter()
+            .map(|h| HostRecord {
+                host: &h.host,
+                key: redact_key(&h.api_key),
+                key_location: h.key_location.as_deref(),
+            })
+            .collect(),
+        normalization: source.normalization,
+        url_states,
+    }
+}
+
+/// When missing URLs of a source are submitted as removed
+fn removals_label(source: &Source) -> String {
+    match source.removal_grace {
+        Some(secs) if source.removal_probe => {
+            format!("after {} missing, if they answer 404/410", queue::format_delay(secs))
+        }
+        Some(secs) => format!("after {} missing", queue::format_delay(secs)),
+        None => "off".dimmed().to_string(),
+    }
+}
+
+/// Watch mode interval of a source, marking the default
+fn poll_interval_label(source: &Source) -> String {
+    match source.poll_interval {
+        Some(secs) => queue::format_delay(secs),
+        None => format!("{} (default)", queue::format_delay(watch::DEFAULT_POLL_INTERVAL)),
     }
 }
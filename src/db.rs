@@ -1,38 +1,70 @@
 //! SQLite database management for storing submitted URLs
 
-/// Copyright (C) 2026 Andre Franca <andre@abf.li>
-///
-/// This program is free software: you can redistribute it and/or modify
-/// it under the terms of the GNU Affero General Public License as published by
-/// the Free Software Foundation, either version 3 of the License, or
-/// (at your option) any later version.
-///
-/// This program is distributed in the hope that it will be useful,
-/// but WITHOUT ANY WARRANTY; without even the implied warranty of
-/// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
-/// GNU Affero General Public License for more details.
-///
-/// You should have received a copy of the GNU Affero General Public License
-/// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::feed::UrlEntry;
+use crate::filter::{Rule, RuleKind};
+use crate::normalize::{TrailingSlash, UrlNormalization};
+use crate::output::say;
+use crate::submit::BatchReport;
 use colored::*;
 use dialoguer::Confirm;
 use rusqlite::{Connection, OptionalExtension, Result as SqlResult};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding the database location
+pub const DB_ENV: &str = "IXFEED_DB";
+
+/// SQLite path of an in-memory database, for tests
+#[cfg(test)]
+pub const IN_MEMORY: &str = ":memory:";
+
+/// Resolve the database path: `--db` first, then `IXFEED_DB`, then
+/// `<data dir>/ixfeed/ixfeed.db`. The parent directory is created if missing.
+/// `:memory:` is rejected: every command opens its own connections, so each
+/// would see a different empty database.
+pub fn db_path(flag: Option<&Path>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = match flag {
+        Some(path) => path.to_path_buf(),
+        None => match std::env::var_os(DB_ENV).filter(|v| !v.is_empty()) {
+            Some(path) => PathBuf::from(path),
+            None => dirs::data_dir()
+                .ok_or("Could not determine data directory")?
+                .join("ixfeed")
+                .join("ixfeed.db"),
+        },
+    };
 
-pub fn db_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let data_dir = dirs::data_dir()
-        .ok_or("Could not determine data directory")?
-        .join("ixfeed");
-    fs::create_dir_all(&data_dir)?;
-    Ok(data_dir.join("ixfeed.db"))
+    if path.as_os_str() == ":memory:" {
+        return Err("An in-memory database can't be used: give a file path (a temporary file for a throwaway database)".into());
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Could not create database directory {}: {}", parent.display(), e))?;
+    }
+    Ok(path)
 }
 
-pub fn init_db() -> Result<Connection, Box<dyn std::error::Error>> {
-    let path = db_path()?;
-    let conn = Connection::open(&path)?;
+/// Open the database at `path` and create or migrate its schema
+pub fn init_db(path: &Path) -> Result<Connection, Box<dyn std::error::Error>> {
+    let conn = Connection::open(path)?;
+    // Watch mode polls sources from several threads, each with its own connection
+    conn.busy_timeout(std::time::Duration::from_secs(30))?;
 
     // Sources table for multiple feeds/sitemaps with per-source config
     conn.execute(
@@ -57,12 +89,91 @@ pub fn init_db() -> Result<Connection, Box<dyn std::error::Error>> {
             url TEXT NOT NULL,
             last_modified TEXT,
             submitted_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            status TEXT NOT NULL DEFAULT 'submitted',
+            last_seen_at INTEGER,
             UNIQUE(source_id, url),
             FOREIGN KEY (source_id) REFERENCES sources(id) ON DELETE CASCADE
         )",
         [],
     )?;
 
+    // Retry queue for submissions that failed with a retryable error
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pending_submissions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_id INTEGER NOT NULL,
+            endpoint TEXT NOT NULL,
+            url TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            last_error TEXT,
+            dead_lettered INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+            UNIQUE(source_id, endpoint, url),
+            FOREIGN KEY (source_id) REFERENCES sources(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Append-only log of every request sent to an IndexNow endpoint
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS submission_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_id INTEGER,
+            endpoint TEXT NOT NULL,
+            url_count INTEGER NOT NULL,
+            http_status INTEGER,
+            response_body TEXT,
+            duration_ms INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    )?;
+
+    // URLs included in each logged request
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS submission_log_urls (
+            log_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            FOREIGN KEY (log_id) REFERENCES submission_log(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_submission_log_urls_log_id ON submission_log_urls(log_id)",
+        [],
+    )?;
+
+    // Validators of fetched feeds and sitemaps for conditional GET, along with
+    // the entries each document contained so a 304 can reuse them
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS http_cache (
+            url TEXT PRIMARY KEY NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            fetched_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS http_cache_entries (
+            document_url TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            url TEXT NOT NULL,
+            last_modified TEXT,
+            FOREIGN KEY (document_url) REFERENCES http_cache(url) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_http_cache_entries_document ON http_cache_entries(document_url)",
+        [],
+    )?;
+
     // Config table (for legacy/global settings)
     conn.execute(
         "CREATE TABLE IF NOT EXISTS config (
@@ -135,6 +246,18 @@ pub fn init_db() -> Result<Connection, Box<dyn std::error::Error>> {
         )?;
     }
 
+    // Migration: add submission status column if it doesn't exist.
+    // Rows stored before this column existed are assumed to have been submitted.
+    let has_status: bool = conn
+        .prepare("SELECT status FROM submitted_urls LIMIT 1")
+        .is_ok();
+    if !has_status {
+        let _ = conn.execute(
+            "ALTER TABLE submitted_urls ADD COLUMN status TEXT NOT NULL DEFAULT 'submitted'",
+            [],
+        );
+    }
+
     // Migration: add per-source config columns if they don't exist
     let has_api_key: bool = conn
         .prepare("SELECT api_key FROM sources LIMIT 1")
@@ -148,6 +271,160 @@ pub fn init_db() -> Result<Connection, Box<dyn std::error::Error>> {
     // Migration: migrate old single-source config to sources table
     migrate_legacy_source(&conn)?;
 
+    // IndexNow endpoints each source submits to
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS source_endpoints (
+            source_id INTEGER NOT NULL,
+            endpoint TEXT NOT NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            UNIQUE(source_id, endpoint),
+            FOREIGN KEY (source_id) REFERENCES sources(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Migration: every source submits at least to its original search engine
+    conn.execute(
+        "INSERT OR IGNORE INTO source_endpoints (source_id, endpoint)
+         SELECT id, searchengine FROM sources
+         WHERE id NOT IN (SELECT source_id FROM source_endpoints)",
+        [],
+    )?;
+
+    // Per-endpoint submission state of each tracked URL
+    let has_url_endpoints: bool = conn
+        .prepare("SELECT 1 FROM url_endpoints LIMIT 1")
+        .is_ok();
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS url_endpoints (
+            source_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            endpoint TEXT NOT NULL,
+            status TEXT NOT NULL,
+            submitted_at INTEGER,
+            UNIQUE(source_id, url, endpoint),
+            FOREIGN KEY (source_id) REFERENCES sources(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Migration: carry the single-endpoint status over to the source's search engine
+    if !has_url_endpoints {
+        conn.execute(
+            "INSERT OR IGNORE INTO url_endpoints (source_id, url, endpoint, status, submitted_at)
+             SELECT u.source_id, u.url, s.searchengine, u.status,
+                    CASE WHEN u.status = 'submitted' THEN u.submitted_at END
+             FROM submitted_urls u JOIN sources s ON s.id = u.source_id
+             WHERE u.status != 'baseline'",
+            [],
+        )?;
+    }
+
+    // Migration: per-source polling interval for watch mode
+    let has_poll_interval: bool = conn
+        .prepare("SELECT poll_interval FROM sources LIMIT 1")
+        .is_ok();
+    if !has_poll_interval {
+        conn.execute("ALTER TABLE sources ADD COLUMN poll_interval INTEGER", [])?;
+    }
+
+    // Include/exclude URL filter rules of each source, applied in order
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS source_filters (
+            source_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            pattern TEXT NOT NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (source_id) REFERENCES sources(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Additional hosts a source may list URLs for, each with its own key
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS source_hosts (
+            source_id INTEGER NOT NULL,
+            host TEXT NOT NULL,
+            api_key TEXT NOT NULL,
+            key_location TEXT,
+            position INTEGER NOT NULL DEFAULT 0,
+            UNIQUE(source_id, host),
+            FOREIGN KEY (source_id) REFERENCES sources(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Key file checks, so a passing key file is fetched at most once a day
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS key_checks (
+            source_id INTEGER NOT NULL,
+            host TEXT NOT NULL,
+            api_key TEXT NOT NULL,
+            key_location TEXT,
+            passed INTEGER NOT NULL,
+            checked_on TEXT NOT NULL,
+            PRIMARY KEY (source_id, host)
+        )",
+        [],
+    )?;
+
+    // Migration: when each URL was last listed by its source. URLs tracked
+    // before this existed count as seen now, so none is removed right away.
+    let has_last_seen: bool = conn
+        .prepare("SELECT last_seen_at FROM submitted_urls LIMIT 1")
+        .is_ok();
+    if !has_last_seen {
+        conn.execute_batch(
+            "ALTER TABLE submitted_urls ADD COLUMN last_seen_at INTEGER;
+             UPDATE submitted_urls SET last_seen_at = strftime('%s', 'now');",
+        )?;
+    }
+
+    // Migration: per-source removal of URLs that dropped out of the source
+    let has_removals: bool = conn
+        .prepare("SELECT removal_grace FROM sources LIMIT 1")
+        .is_ok();
+    if !has_removals {
+        conn.execute_batch(
+            "ALTER TABLE sources ADD COLUMN removal_grace INTEGER;
+             ALTER TABLE sources ADD COLUMN removal_probe INTEGER NOT NULL DEFAULT 1;",
+        )?;
+    }
+
+    // Migration: key file location of a source, if it is not at the host root
+    let has_key_location: bool = conn
+        .prepare("SELECT key_location FROM sources LIMIT 1")
+        .is_ok();
+    if !has_key_location {
+        conn.execute("ALTER TABLE sources ADD COLUMN key_location TEXT", [])?;
+    }
+
+    // Migration: per-source URL normalization settings. URLs stored before
+    // normalization existed are rewritten once with the defaults.
+    let has_normalization: bool = conn
+        .prepare("SELECT trailing_slash FROM sources LIMIT 1")
+        .is_ok();
+    if !has_normalization {
+        conn.execute_batch(
+            "ALTER TABLE sources ADD COLUMN trailing_slash TEXT NOT NULL DEFAULT 'keep';
+             ALTER TABLE sources ADD COLUMN strip_tracking INTEGER NOT NULL DEFAULT 1;
+             ALTER TABLE sources ADD COLUMN sort_query INTEGER NOT NULL DEFAULT 0;",
+        )?;
+        let tx = conn.unchecked_transaction()?;
+        for source in get_all_sources(&tx)? {
+            normalize_source_urls(&tx, source.id, &source.normalization)?;
+        }
+        tx.commit()?;
+    }
+
+    // Migration: host each logged request was made for
+    let has_log_host: bool = conn
+        .prepare("SELECT host FROM submission_log LIMIT 1")
+        .is_ok();
+    if !has_log_host {
+        conn.execute("ALTER TABLE submission_log ADD COLUMN host TEXT", [])?;
+    }
+
     Ok(conn)
 }
 
@@ -216,15 +493,54 @@ pub struct Source {
     pub source_url: String,
     pub api_key: String,
     pub host: String,
-    pub searchengine: String,
+    /// URL of the key file, if it is not at `https://{host}/{key}.txt`
+    pub key_location: Option<String>,
+    /// IndexNow endpoints (search engine hosts) to submit to, in order
+    pub endpoints: Vec<String>,
     pub first_run_completed: bool,
+    /// Seconds between polls in watch mode; `None` uses the default
+    pub poll_interval: Option<i64>,
+    /// URL include/exclude rules, in order
+    pub filters: Vec<Rule>,
+    /// How discovered URLs are normalized before they are compared and stored
+    pub normalization: UrlNormalization,
+    /// Hosts besides `host` whose URLs the source may list, in order
+    pub extra_hosts: Vec<SourceHost>,
+    /// Seconds a URL must be missing from the source before it is submitted
+    /// as removed; `None` never submits removals
+    pub removal_grace: Option<i64>,
+    /// Only submit a missing URL as removed if it answers 404 or 410
+    pub removal_probe: bool,
+}
+
+/// A host URLs are submitted for, with the IndexNow key that proves ownership of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceHost {
+    pub host: String,
+    pub api_key: String,
+    /// URL of the key file, if it is not at `https://{host}/{key}.txt`
+    pub key_location: Option<String>,
+}
+
+impl Source {
+    /// Every host the source submits for, its own host first
+    pub fn hosts(&self) -> Vec<SourceHost> {
+        let primary = SourceHost {
+            host: self.host.clone(),
+            api_key: self.api_key.clone(),
+            key_location: self.key_location.clone(),
+        };
+        std::iter::once(primary).chain(self.extra_hosts.iter().cloned()).collect()
+    }
 }
 
 pub fn get_all_sources(conn: &Connection) -> SqlResult<Vec<Source>> {
     let mut stmt = conn.prepare(
-        "SELECT id, source_type, source_url, api_key, host, searchengine, first_run_completed FROM sources ORDER BY id"
+        "SELECT id, source_type, source_url, api_key, host, first_run_completed, poll_interval,
+                trailing_slash, strip_tracking, sort_query, key_location, removal_grace, removal_probe
+         FROM sources ORDER BY id"
     )?;
-    let sources = stmt
+    let mut sources: Vec<Source> = stmt
         .query_map([], |row| {
             Ok(Source {
                 id: row.get(0)?,
@@ -232,34 +548,261 @@ pub fn get_all_sources(conn: &Connection) -> SqlResult<Vec<Source>> {
                 source_url: row.get(2)?,
                 api_key: row.get(3)?,
                 host: row.get(4)?,
-                searchengine: row.get(5)?,
-                first_run_completed: row.get::<_, i64>(6)? == 1,
+                key_location: row.get(10)?,
+                endpoints: Vec::new(),
+                first_run_completed: row.get::<_, i64>(5)? == 1,
+                poll_interval: row.get(6)?,
+                filters: Vec::new(),
+                normalization: UrlNormalization {
+                    trailing_slash: TrailingSlash::from_db(&row.get::<_, String>(7)?),
+                    strip_tracking: row.get::<_, i64>(8)? == 1,
+                    sort_query: row.get::<_, i64>(9)? == 1,
+                },
+                extra_hosts: Vec::new(),
+                removal_grace: row.get(11)?,
+                removal_probe: row.get::<_, i64>(12)? == 1,
             })
         })?
         .filter_map(|r| r.ok())
         .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT source_id, endpoint FROM source_endpoints ORDER BY source_id, position, rowid"
+    )?;
+    let mut endpoints: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))? {
+        let (source_id, endpoint) = row?;
+        endpoints.entry(source_id).or_default().push(endpoint);
+    }
+    let mut stmt = conn.prepare(
+        "SELECT source_id, kind, pattern FROM source_filters ORDER BY source_id, position, rowid"
+    )?;
+    let mut filters: HashMap<i64, Vec<Rule>> = HashMap::new();
+    for row in stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })? {
+        let (source_id, kind, pattern) = row?;
+        filters.entry(source_id).or_default().push(Rule {
+            kind: RuleKind::from_db(&kind),
+            pattern,
+        });
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT source_id, host, api_key, key_location FROM source_hosts ORDER BY source_id, position, rowid"
+    )?;
+    let mut extra_hosts: HashMap<i64, Vec<SourceHost>> = HashMap::new();
+    for row in stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            SourceHost {
+                host: row.get(1)?,
+                api_key: row.get(2)?,
+                key_location: row.get(3)?,
+            },
+        ))
+    })? {
+        let (source_id, host) = row?;
+        extra_hosts.entry(source_id).or_default().push(host);
+    }
+
+    for source in &mut sources {
+        source.endpoints = endpoints.remove(&source.id).unwrap_or_default();
+        source.filters = filters.remove(&source.id).unwrap_or_default();
+        source.extra_hosts = extra_hosts.remove(&source.id).unwrap_or_default();
+    }
+
     Ok(sources)
 }
 
-pub fn add_source(conn: &Connection, source_type: &str, source_url: &str, api_key: &str, host: &str, searchengine: &str) -> SqlResult<i64> {
+/// Set how often watch mode polls a source, or `None` for the default
+pub fn set_source_poll_interval(conn: &Connection, id: i64, seconds: Option<i64>) -> SqlResult<()> {
+    conn.execute(
+        "UPDATE sources SET poll_interval = ?1 WHERE id = ?2",
+        rusqlite::params![seconds, id],
+    )?;
+    Ok(())
+}
+
+/// Set where the key file of a source is, or `None` for `https://{host}/{key}.txt`
+pub fn set_source_key_location(conn: &Connection, id: i64, key_location: Option<&str>) -> SqlResult<()> {
+    conn.execute(
+        "UPDATE sources SET key_location = ?1 WHERE id = ?2",
+        rusqlite::params![key_location, id],
+    )?;
+    Ok(())
+}
+
+/// Set after how long missing URLs of a source are submitted as removed
+/// (`None` to never), and whether they are probed for 404/410 first
+pub fn set_source_removals(conn: &Connection, id: i64, grace: Option<i64>, probe: bool) -> SqlResult<()> {
+    conn.execute(
+        "UPDATE sources SET removal_grace = ?1, removal_probe = ?2 WHERE id = ?3",
+        rusqlite::params![grace, probe as i64, id],
+    )?;
+    Ok(())
+}
+
+/// Replace the URL filter rules of a source
+pub fn set_source_filters(conn: &Connection, id: i64, rules: &[Rule]) -> SqlResult<()> {
+    conn.execute("DELETE FROM source_filters WHERE source_id = ?1", [id])?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO source_filters (source_id, kind, pattern, position) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for (position, rule) in rules.iter().enumerate() {
+        stmt.execute(rusqlite::params![id, rule.kind.as_str(), rule.pattern, position as i64])?;
+    }
+    Ok(())
+}
+
+/// Replace the additional hosts of a source
+pub fn set_source_extra_hosts(conn: &Connection, id: i64, hosts: &[SourceHost]) -> SqlResult<()> {
+    conn.execute("DELETE FROM source_hosts WHERE source_id = ?1", [id])?;
+    let mut stmt = conn.prepare(
+        "INSERT OR REPLACE INTO source_hosts (source_id, host, api_key, key_location, position)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for (position, host) in hosts.iter().enumerate() {
+        stmt.execute(rusqlite::params![id, host.host, host.api_key, host.key_location, position as i64])?;
+    }
+    Ok(())
+}
+
+/// Change the URL normalization of a source and rewrite its tracked URLs to match
+pub fn set_source_normalization(conn: &Connection, id: i64, normalization: &UrlNormalization) -> SqlResult<usize> {
     conn.execute(
-        "INSERT INTO sources (source_type, source_url, api_key, host, searchengine) VALUES (?1, ?2, ?3, ?4, ?5)",
-        rusqlite::params![source_type, source_url, api_key, host, searchengine],
+        "UPDATE sources SET trailing_slash = ?1, strip_tracking = ?2, sort_query = ?3 WHERE id = ?4",
+        rusqlite::params![
+            normalization.trailing_slash.as_str(),
+            normalization.strip_tracking as i64,
+            normalization.sort_query as i64,
+            id
+        ],
     )?;
-    Ok(conn.last_insert_rowid())
+    normalize_source_urls(conn, id, normalization)
+}
+
+/// Rewrite the tracked, per-endpoint and queued URLs of a source in normalized
+/// form. When two stored URLs normalize to the same one, the row already in
+/// normalized form is kept. Returns the number of rewritten URLs.
+pub fn normalize_source_urls(conn: &Connection, source_id: i64, normalization: &UrlNormalization) -> SqlResult<usize> {
+    let urls: Vec<String> = conn
+        .prepare(
+            "SELECT url FROM submitted_urls WHERE source_id = ?1
+             UNION SELECT url FROM pending_submissions WHERE source_id = ?1",
+        )?
+        .query_map([source_id], |row| row.get(0))?
+        .collect::<SqlResult<_>>()?;
+
+    let mut rewritten = 0;
+    for url in &urls {
+        let normalized = normalization.apply(url);
+        if normalized == *url {
+            continue;
+        }
+        // Keep an accepted submission when the normalized URL is tracked but not submitted
+        conn.execute(
+            "UPDATE submitted_urls SET status = 'submitted', submitted_at = old.submitted_at
+             FROM (SELECT submitted_at FROM submitted_urls WHERE source_id = ?2 AND url = ?3 AND status = 'submitted') AS old
+             WHERE source_id = ?2 AND url = ?1 AND status != 'submitted'",
+            rusqlite::params![normalized, source_id, url],
+        )?;
+        conn.execute(
+            "UPDATE url_endpoints SET status = 'submitted', submitted_at = old.submitted_at
+             FROM (SELECT endpoint, submitted_at FROM url_endpoints WHERE source_id = ?2 AND url = ?3 AND status = 'submitted') AS old
+             WHERE url_endpoints.source_id = ?2 AND url_endpoints.url = ?1
+               AND url_endpoints.endpoint = old.endpoint AND url_endpoints.status != 'submitted'",
+            rusqlite::params![normalized, source_id, url],
+        )?;
+        for table in ["submitted_urls", "url_endpoints", "pending_submissions"] {
+            // A conflicting row means the normalized URL is already tracked; drop the variant
+            conn.execute(
+                &format!("UPDATE OR IGNORE {} SET url = ?1 WHERE source_id = ?2 AND url = ?3", table),
+                rusqlite::params![normalized, source_id, url],
+            )?;
+            conn.execute(
+                &format!("DELETE FROM {} WHERE source_id = ?1 AND url = ?2", table),
+                rusqlite::params![source_id, url],
+            )?;
+        }
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+/// Whether the key file of a host passed a check today with its current key and location
+pub fn key_check_passed_today(conn: &Connection, source_id: i64, host: &SourceHost) -> SqlResult<bool> {
+    let passed: Option<bool> = conn
+        .query_row(
+            "SELECT 1 FROM key_checks
+             WHERE source_id = ?1 AND host = ?2 AND api_key = ?3 AND key_location IS ?4
+               AND passed = 1 AND checked_on = date('now')",
+            rusqlite::params![source_id, host.host, host.api_key, host.key_location],
+            |_| Ok(true),
+        )
+        .optional()?;
+    Ok(passed.is_some())
+}
+
+/// Record the outcome of a key file check, replacing the previous one for the host
+pub fn record_key_check(conn: &Connection, source_id: i64, host: &SourceHost, passed: bool) -> SqlResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO key_checks (source_id, host, api_key, key_location, passed, checked_on)
+         VALUES (?1, ?2, ?3, ?4, ?5, date('now'))",
+        rusqlite::params![source_id, host.host, host.api_key, host.key_location, passed as i64],
+    )?;
+    Ok(())
 }
 
-pub fn update_source(conn: &Connection, id: i64, source_type: &str, source_url: &str, api_key: &str, host: &str, searchengine: &str) -> SqlResult<bool> {
+/// Replace the IndexNow endpoints of a source. The first endpoint is also kept
+/// in `sources.searchengine` for older versions.
+pub fn set_source_endpoints(conn: &Connection, id: i64, endpoints: &[String]) -> SqlResult<()> {
+    conn.execute("DELETE FROM source_endpoints WHERE source_id = ?1", [id])?;
+    let mut stmt = conn.prepare(
+        "INSERT OR IGNORE INTO source_endpoints (source_id, endpoint, position) VALUES (?1, ?2, ?3)",
+    )?;
+    for (position, endpoint) in endpoints.iter().enumerate() {
+        stmt.execute(rusqlite::params![id, endpoint, position as i64])?;
+    }
+    if let Some(primary) = endpoints.first() {
+        conn.execute(
+            "UPDATE sources SET searchengine = ?1 WHERE id = ?2",
+            rusqlite::params![primary, id],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn add_source(conn: &Connection, source_type: &str, source_url: &str, api_key: &str, host: &str, endpoints: &[String]) -> SqlResult<i64> {
+    conn.execute(
+        "INSERT INTO sources (source_type, source_url, api_key, host) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![source_type, source_url, api_key, host],
+    )?;
+    let id = conn.last_insert_rowid();
+    set_source_endpoints(conn, id, endpoints)?;
+    Ok(id)
+}
+
+pub fn update_source(conn: &Connection, id: i64, source_type: &str, source_url: &str, api_key: &str, host: &str, endpoints: &[String]) -> SqlResult<bool> {
     let rows = conn.execute(
-        "UPDATE sources SET source_type = ?1, source_url = ?2, api_key = ?3, host = ?4, searchengine = ?5 WHERE id = ?6",
-        rusqlite::params![source_type, source_url, api_key, host, searchengine, id],
+        "UPDATE sources SET source_type = ?1, source_url = ?2, api_key = ?3, host = ?4 WHERE id = ?5",
+        rusqlite::params![source_type, source_url, api_key, host, id],
     )?;
+    if rows > 0 {
+        set_source_endpoints(conn, id, endpoints)?;
+    }
     Ok(rows > 0)
 }
 
 pub fn remove_source(conn: &Connection, id: i64) -> SqlResult<bool> {
     // First delete all URLs associated with this source
     conn.execute("DELETE FROM submitted_urls WHERE source_id = ?1", [id])?;
+    conn.execute("DELETE FROM url_endpoints WHERE source_id = ?1", [id])?;
+    conn.execute("DELETE FROM pending_submissions WHERE source_id = ?1", [id])?;
+    conn.execute("DELETE FROM source_endpoints WHERE source_id = ?1", [id])?;
+    conn.execute("DELETE FROM source_filters WHERE source_id = ?1", [id])?;
+    conn.execute("DELETE FROM source_hosts WHERE source_id = ?1", [id])?;
+    conn.execute("DELETE FROM key_checks WHERE source_id = ?1", [id])?;
     // Then delete the source
     let rows = conn.execute("DELETE FROM sources WHERE id = ?1", [id])?;
     Ok(rows > 0)
@@ -274,6 +817,51 @@ pub fn source_exists(conn: &Connection, source_url: &str) -> SqlResult<bool> {
     Ok(result.is_some())
 }
 
+/// Every URL tracked for a source with when it was last seen, in Unix seconds
+pub fn get_tracked_urls(conn: &Connection, source_id: i64) -> SqlResult<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, COALESCE(last_seen_at, submitted_at) FROM submitted_urls WHERE source_id = ?1 ORDER BY url",
+    )?;
+    let urls = stmt
+        .query_map([source_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(urls)
+}
+
+/// Stop tracking URLs of a source, along with their endpoint states and
+/// queued retries. The submission history is kept.
+pub fn forget_urls(conn: &Connection, source_id: i64, urls: &[&str]) -> SqlResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    for table in ["submitted_urls", "url_endpoints", "pending_submissions"] {
+        let mut stmt = tx.prepare(&format!("DELETE FROM {} WHERE source_id = ?1 AND url = ?2", table))?;
+        for url in urls {
+            stmt.execute(rusqlite::params![source_id, url])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Forget every URL of a source so its next run is a first run again. The
+/// source's settings and submission history are kept.
+pub fn reset_source(conn: &Connection, source_id: i64) -> SqlResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM submitted_urls WHERE source_id = ?1", [source_id])?;
+    tx.execute("DELETE FROM url_endpoints WHERE source_id = ?1", [source_id])?;
+    tx.execute("DELETE FROM pending_submissions WHERE source_id = ?1", [source_id])?;
+    tx.execute("UPDATE sources SET first_run_completed = 0 WHERE id = ?1", [source_id])?;
+    tx.commit()
+}
+
+/// Number of URLs tracked for a source
+pub fn count_urls_for_source(conn: &Connection, source_id: i64) -> SqlResult<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM submitted_urls WHERE source_id = ?1",
+        [source_id],
+        |row| row.get(0),
+    )
+}
+
 // ============================================================================
 // First run detection (per source)
 // ============================================================================
@@ -302,25 +890,685 @@ pub fn mark_source_first_run_completed(conn: &Connection, source_id: i64) -> Sql
 // URL management
 // ============================================================================
 
-/// Get URLs with dates for a specific source
-pub fn get_urls_with_dates_for_source(conn: &Connection, source_id: i64) -> SqlResult<HashMap<String, Option<String>>> {
-    let mut stmt = conn.prepare("SELECT url, last_modified FROM submitted_urls WHERE source_id = ?1")?;
+/// Submission state of a tracked URL. Tracked per endpoint in `url_endpoints`;
+/// `submitted_urls.status` holds the overall state across all endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlStatus {
+    /// Recorded and about to be submitted, but not yet accepted by IndexNow
+    Pending,
+    /// Accepted by IndexNow (HTTP 200/202)
+    Submitted,
+    /// Submission was attempted and rejected or did not complete
+    Failed,
+    /// Stored on first run without being submitted
+    Baseline,
+    /// Waiting in the retry queue after a retryable failure
+    Queued,
+    /// Retry queue gave up after the maximum number of attempts
+    DeadLettered,
+    /// Not on the source's host, so never submitted
+    OffHost,
+    /// Dropped out of the source and submitted as removed
+    Removed,
+}
+
+impl UrlStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UrlStatus::Pending => "pending",
+            UrlStatus::Submitted => "submitted",
+            UrlStatus::Failed => "failed",
+            UrlStatus::Baseline => "baseline",
+            UrlStatus::Queued => "queued",
+            UrlStatus::DeadLettered => "dead_lettered",
+            UrlStatus::OffHost => "off_host",
+            UrlStatus::Removed => "removed",
+        }
+    }
+
+    fn from_db(value: &str) -> Self {
+        match value {
+            "pending" => UrlStatus::Pending,
+            "failed" => UrlStatus::Failed,
+            "baseline" => UrlStatus::Baseline,
+            "queued" => UrlStatus::Queued,
+            "dead_lettered" => UrlStatus::DeadLettered,
+            "off_host" => UrlStatus::OffHost,
+            "removed" => UrlStatus::Removed,
+            _ => UrlStatus::Submitted,
+        }
+    }
+
+}
+
+/// A URL as stored in the database for a source
+#[derive(Debug, Clone)]
+pub struct StoredUrl {
+    pub last_modified: Option<String>,
+}
+
+/// Get URLs with dates for a specific source. Quarantined off-host URLs and
+/// removed URLs are left out, so they count as new if they are listed again.
+pub fn get_urls_with_dates_for_source(conn: &Connection, source_id: i64) -> SqlResult<HashMap<String, StoredUrl>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, last_modified FROM submitted_urls
+         WHERE source_id = ?1 AND status NOT IN ('off_host', 'removed')",
+    )?;
     let map = stmt
         .query_map([source_id], |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            Ok((
+                row.get::<_, String>(0)?,
+                StoredUrl {
+                    last_modified: row.get(1)?,
+                },
+            ))
         })?
         .filter_map(|r| r.ok())
         .collect();
     Ok(map)
 }
 
-/// Add URL with its modification date for a specific source
-pub fn add_url_with_date_for_source(conn: &Connection, source_id: i64, url: &str, last_modified: Option<&str>) -> SqlResult<()> {
+/// Record a URL with its modification date and submission status for a specific source.
+///
+/// `submitted_at` is only bumped when the status is `Submitted`.
+pub fn record_url_for_source(conn: &Connection, source_id: i64, url: &str, last_modified: Option<&str>, status: UrlStatus) -> SqlResult<()> {
+    conn.execute(
+        "INSERT INTO submitted_urls (source_id, url, last_modified, status, last_seen_at)
+         VALUES (?1, ?2, ?3, ?4, strftime('%s', 'now'))
+         ON CONFLICT(source_id, url) DO UPDATE SET
+            last_modified = ?3,
+            status = ?4,
+            last_seen_at = strftime('%s', 'now'),
+            submitted_at = CASE WHEN ?4 = 'submitted' THEN strftime('%s', 'now') ELSE submitted_at END",
+        rusqlite::params![source_id, url, last_modified, status.as_str()],
+    )?;
+    Ok(())
+}
+
+/// Tracked URLs of a source with the given overall status
+pub fn get_urls_with_status(conn: &Connection, source_id: i64, status: UrlStatus) -> SqlResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT url FROM submitted_urls WHERE source_id = ?1 AND status = ?2 ORDER BY url",
+    )?;
+    let urls = stmt
+        .query_map(rusqlite::params![source_id, status.as_str()], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(urls)
+}
+
+/// Quarantine off-host URLs: keep them tracked so they are reported only once,
+/// and drop their endpoint states and queued retries so they are never submitted
+pub fn quarantine_urls(conn: &Connection, source_id: i64, urls: &[&str]) -> SqlResult<()> {
+    for url in urls {
+        record_url_for_source(conn, source_id, url, None, UrlStatus::OffHost)?;
+        conn.execute(
+            "DELETE FROM url_endpoints WHERE source_id = ?1 AND url = ?2",
+            rusqlite::params![source_id, url],
+        )?;
+        conn.execute(
+            "DELETE FROM pending_submissions WHERE source_id = ?1 AND url = ?2",
+            rusqlite::params![source_id, url],
+        )?;
+    }
+    Ok(())
+}
+
+/// Tracked URLs of a source last submitted within an inclusive range of
+/// `YYYY-MM-DD` dates (UTC), either end optional. Quarantined and removed
+/// URLs are left out.
+pub fn get_urls_submitted_between(
+    conn: &Connection,
+    source_id: i64,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> SqlResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT url FROM submitted_urls
+         WHERE source_id = ?1 AND status NOT IN ('off_host', 'removed')
+           AND (?2 IS NULL OR submitted_at >= CAST(strftime('%s', ?2) AS INTEGER))
+           AND (?3 IS NULL OR submitted_at < CAST(strftime('%s', ?3, '+1 day') AS INTEGER))
+         ORDER BY url",
+    )?;
+    let urls = stmt
+        .query_map(rusqlite::params![source_id, since, until], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(urls)
+}
+
+/// Track URLs under a source as pending, for URLs submitted by hand. URLs
+/// already tracked keep their date; removed ones are tracked again.
+pub fn track_urls(conn: &Connection, source_id: i64, urls: &[&str]) -> SqlResult<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO submitted_urls (source_id, url, status, last_seen_at)
+         VALUES (?1, ?2, 'pending', strftime('%s', 'now'))
+         ON CONFLICT(source_id, url) DO UPDATE SET
+            status = CASE WHEN status = 'removed' THEN 'pending' ELSE status END,
+            last_seen_at = strftime('%s', 'now')",
+    )?;
+    for url in urls {
+        stmt.execute(rusqlite::params![source_id, url])?;
+    }
+    Ok(())
+}
+
+/// Note that a source still lists these URLs
+pub fn mark_urls_seen(conn: &Connection, source_id: i64, urls: &[&str]) -> SqlResult<()> {
+    let mut stmt = conn.prepare(
+        "UPDATE submitted_urls SET last_seen_at = strftime('%s', 'now') WHERE source_id = ?1 AND url = ?2",
+    )?;
+    for url in urls {
+        stmt.execute(rusqlite::params![source_id, url])?;
+    }
+    Ok(())
+}
+
+/// Tracked URLs of a source that were last seen before `seen_before` (Unix
+/// seconds) and are not already removed or quarantined
+pub fn get_unseen_urls(conn: &Connection, source_id: i64, seen_before: i64) -> SqlResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT url FROM submitted_urls
+         WHERE source_id = ?1 AND status NOT IN ('off_host', 'removed')
+           AND COALESCE(last_seen_at, submitted_at) < ?2
+         ORDER BY url",
+    )?;
+    let urls = stmt
+        .query_map(rusqlite::params![source_id, seen_before], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(urls)
+}
+
+/// Mark URLs as removed from their source and drop their queued retries. Their
+/// endpoint states are reset by the removal submission that follows.
+pub fn mark_urls_removed(conn: &Connection, source_id: i64, urls: &[&str]) -> SqlResult<()> {
+    for url in urls {
+        conn.execute(
+            "UPDATE submitted_urls SET status = 'removed' WHERE source_id = ?1 AND url = ?2",
+            rusqlite::params![source_id, url],
+        )?;
+        conn.execute(
+            "DELETE FROM pending_submissions WHERE source_id = ?1 AND url = ?2",
+            rusqlite::params![source_id, url],
+        )?;
+    }
+    Ok(())
+}
+
+/// Record URLs as pending on each of the given endpoints
+pub fn record_pending_endpoints(conn: &Connection, source_id: i64, urls: &[&str], endpoints: &[String]) -> SqlResult<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO url_endpoints (source_id, url, endpoint, status) VALUES (?1, ?2, ?3, 'pending')
+         ON CONFLICT(source_id, url, endpoint) DO UPDATE SET status = 'pending'",
+    )?;
+    for url in urls {
+        for endpoint in endpoints {
+            stmt.execute(rusqlite::params![source_id, url, endpoint])?;
+        }
+    }
+    Ok(())
+}
+
+/// Get URLs that still need to be (re)submitted to an endpoint.
+/// Queued URLs are handled by the retry queue instead.
+pub fn get_unsubmitted_urls_for_endpoint(conn: &Connection, source_id: i64, endpoint: &str) -> SqlResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT url FROM url_endpoints
+         WHERE source_id = ?1 AND endpoint = ?2 AND status IN ('pending', 'failed')
+         ORDER BY url",
+    )?;
+    let urls = stmt
+        .query_map(rusqlite::params![source_id, endpoint], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(urls)
+}
+
+/// Recompute the overall status of a URL from its per-endpoint states.
+/// URLs without endpoint states (such as baseline URLs) and removed URLs keep
+/// their status.
+fn refresh_url_status(conn: &Connection, source_id: i64, url: &str) -> SqlResult<()> {
+    conn.execute(
+        "UPDATE submitted_urls SET status = COALESCE((
+            SELECT CASE
+                WHEN COUNT(*) = 0 THEN NULL
+                WHEN SUM(e.status = 'failed') > 0 THEN 'failed'
+                WHEN SUM(e.status = 'pending') > 0 THEN 'pending'
+                WHEN SUM(e.status = 'queued') > 0 THEN 'queued'
+                WHEN SUM(e.status = 'dead_lettered') > 0 THEN 'dead_lettered'
+                ELSE 'submitted'
+            END
+            FROM url_endpoints e WHERE e.source_id = ?1 AND e.url = ?2
+         ), status)
+         WHERE source_id = ?1 AND url = ?2 AND status != 'removed'",
+        rusqlite::params![source_id, url],
+    )?;
+    Ok(())
+}
+
+/// Mark URLs as accepted by an endpoint and drop them from its retry queue
+pub fn mark_urls_submitted(conn: &Connection, source_id: i64, endpoint: &str, urls: &[&str]) -> SqlResult<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO url_endpoints (source_id, url, endpoint, status, submitted_at)
+         VALUES (?1, ?2, ?3, 'submitted', strftime('%s', 'now'))
+         ON CONFLICT(source_id, url, endpoint) DO UPDATE SET
+            status = 'submitted',
+            submitted_at = strftime('%s', 'now')",
+    )?;
+    let mut touch = conn.prepare(
+        "UPDATE submitted_urls SET submitted_at = strftime('%s', 'now') WHERE source_id = ?1 AND url = ?2",
+    )?;
+    let mut dequeue = conn.prepare(
+        "DELETE FROM pending_submissions WHERE source_id = ?1 AND endpoint = ?2 AND url = ?3",
+    )?;
+    for url in urls {
+        stmt.execute(rusqlite::params![source_id, url, endpoint])?;
+        touch.execute(rusqlite::params![source_id, url])?;
+        dequeue.execute(rusqlite::params![source_id, endpoint, url])?;
+        refresh_url_status(conn, source_id, url)?;
+    }
+    Ok(())
+}
+
+/// Set the submission status of URLs on an endpoint
+pub fn set_urls_status(conn: &Connection, source_id: i64, endpoint: &str, urls: &[&str], status: UrlStatus) -> SqlResult<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO url_endpoints (source_id, url, endpoint, status) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(source_id, url, endpoint) DO UPDATE SET status = ?4",
+    )?;
+    for url in urls {
+        stmt.execute(rusqlite::params![source_id, url, endpoint, status.as_str()])?;
+        refresh_url_status(conn, source_id, url)?;
+    }
+    Ok(())
+}
+
+/// Number of URLs per submission status on each endpoint of a source
+pub fn get_endpoint_stats(conn: &Connection, source_id: i64) -> SqlResult<Vec<(String, UrlStatus, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT endpoint, status, COUNT(*) FROM url_endpoints
+         WHERE source_id = ?1 GROUP BY endpoint, status ORDER BY endpoint, status",
+    )?;
+    let stats = stmt
+        .query_map([source_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                UrlStatus::from_db(&row.get::<_, String>(1)?),
+                row.get::<_, i64>(2)?,
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(stats)
+}
+
+// ============================================================================
+// Retry queue
+// ============================================================================
+
+/// A submission waiting in (or dead-lettered from) the retry queue
+#[derive(Debug, Clone)]
+pub struct QueuedSubmission {
+    pub source_id: i64,
+    pub endpoint: String,
+    pub url: String,
+    pub reason: String,
+    pub attempts: u32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub dead_lettered: bool,
+}
+
+const QUEUE_COLUMNS: &str =
+    "source_id, endpoint, url, reason, attempts, next_attempt_at, last_error, dead_lettered";
+
+fn queued_submission_from_row(row: &rusqlite::Row) -> SqlResult<QueuedSubmission> {
+    Ok(QueuedSubmission {
+        source_id: row.get(0)?,
+        endpoint: row.get(1)?,
+        url: row.get(2)?,
+        reason: row.get(3)?,
+        attempts: row.get(4)?,
+        next_attempt_at: row.get(5)?,
+        last_error: row.get(6)?,
+        dead_lettered: row.get::<_, i64>(7)? == 1,
+    })
+}
+
+/// Add or update a queued submission. The original reason is kept when a URL is re-queued,
+/// and so are its attempts and dead-letter flag, so re-queueing never resets the attempt count.
+pub fn enqueue_submission(conn: &Connection, item: &QueuedSubmission) -> SqlResult<()> {
     conn.execute(
-        "INSERT INTO submitted_urls (source_id, url, last_modified) VALUES (?1, ?2, ?3)
-         ON CONFLICT(source_id, url) DO UPDATE SET last_modified = ?3, submitted_at = strftime('%s', 'now')",
-        rusqlite::params![source_id, url, last_modified],
+        "INSERT INTO pending_submissions (source_id, endpoint, url, reason, attempts, next_attempt_at, last_error, dead_lettered)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(source_id, endpoint, url) DO UPDATE SET
+            attempts = MAX(attempts, excluded.attempts),
+            next_attempt_at = excluded.next_attempt_at,
+            last_error = excluded.last_error,
+            dead_lettered = MAX(dead_lettered, excluded.dead_lettered)",
+        rusqlite::params![
+            item.source_id,
+            item.endpoint,
+            item.url,
+            item.reason,
+            item.attempts,
+            item.next_attempt_at,
+            item.last_error,
+            item.dead_lettered as i64
+        ],
+    )?;
+    Ok(())
+}
+
+/// Remove URLs from the retry queue without marking them as submitted
+pub fn dequeue_submissions(conn: &Connection, source_id: i64, endpoint: &str, urls: &[&str]) -> SqlResult<()> {
+    let mut stmt = conn.prepare(
+        "DELETE FROM pending_submissions WHERE source_id = ?1 AND endpoint = ?2 AND url = ?3",
+    )?;
+    for url in urls {
+        stmt.execute(rusqlite::params![source_id, endpoint, url])?;
+    }
+    Ok(())
+}
+
+/// The queue entry of a URL for one endpoint, if it has one
+pub fn get_queued_submission(conn: &Connection, source_id: i64, endpoint: &str, url: &str) -> SqlResult<Option<QueuedSubmission>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM pending_submissions WHERE source_id = ?1 AND endpoint = ?2 AND url = ?3",
+            QUEUE_COLUMNS
+        ),
+        rusqlite::params![source_id, endpoint, url],
+        queued_submission_from_row,
+    )
+    .optional()
+}
+
+/// Get queued submissions for a source/endpoint that are due for another attempt
+pub fn get_due_submissions(conn: &Connection, source_id: i64, endpoint: &str, now: i64) -> SqlResult<Vec<QueuedSubmission>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM pending_submissions
+         WHERE source_id = ?1 AND endpoint = ?2 AND dead_lettered = 0 AND next_attempt_at <= ?3
+         ORDER BY id",
+        QUEUE_COLUMNS
+    ))?;
+    let items = stmt
+        .query_map(rusqlite::params![source_id, endpoint, now], queued_submission_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+/// Get every queued and dead-lettered submission
+pub fn get_queued_submissions(conn: &Connection) -> SqlResult<Vec<QueuedSubmission>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM pending_submissions ORDER BY dead_lettered, source_id, next_attempt_at, id",
+        QUEUE_COLUMNS
+    ))?;
+    let items = stmt
+        .query_map([], queued_submission_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+/// If a source/endpoint is backing off, return the time its queue is next due
+pub fn get_backoff_until(conn: &Connection, source_id: i64, endpoint: &str, now: i64) -> SqlResult<Option<i64>> {
+    conn.query_row(
+        "SELECT MAX(next_attempt_at) FROM pending_submissions
+         WHERE source_id = ?1 AND endpoint = ?2 AND dead_lettered = 0 AND next_attempt_at > ?3",
+        rusqlite::params![source_id, endpoint, now],
+        |row| row.get(0),
+    )
+}
+
+// ============================================================================
+// Submission history
+// ============================================================================
+
+/// Append a request to the submission log along with the URLs it carried
+pub fn log_submission(conn: &Connection, source_id: Option<i64>, report: &BatchReport) -> SqlResult<()> {
+    let mut kinds: Vec<&str> = Vec::new();
+    for entry in report.entries {
+        let kind = entry.reason.kind();
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO submission_log (source_id, endpoint, host, url_count, http_status, response_body, duration_ms, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            source_id,
+            report.endpoint,
+            report.host,
+            report.entries.len() as i64,
+            report.status,
+            report.response_body,
+            report.duration.as_millis() as i64,
+            kinds.join(",")
+        ],
+    )?;
+    let log_id = conn.last_insert_rowid();
+
+    let mut stmt = conn.prepare(
+        "INSERT INTO submission_log_urls (log_id, url, reason) VALUES (?1, ?2, ?3)",
+    )?;
+    for entry in report.entries {
+        stmt.execute(rusqlite::params![log_id, entry.url, entry.reason.kind()])?;
+    }
+    Ok(())
+}
+
+/// Which HTTP results to include when querying the submission log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFilter {
+    /// Accepted requests (2xx)
+    Success,
+    /// Rejected requests and requests that never got a response
+    Failed,
+    /// A specific HTTP status code
+    Code(u16),
+}
+
+/// Filters for querying the submission log. Dates are `YYYY-MM-DD` (UTC) and inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub source_ids: Option<Vec<i64>>,
+    pub url_contains: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub status: Option<StatusFilter>,
+    pub limit: usize,
+}
+
+/// A logged request to an IndexNow endpoint
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub id: i64,
+    pub source_id: Option<i64>,
+    pub endpoint: String,
+    /// Host the request was made for; unknown for requests logged by older versions
+    pub host: Option<String>,
+    pub url_count: i64,
+    pub http_status: Option<u16>,
+    pub response_body: Option<String>,
+    pub duration_ms: i64,
+    pub reason: String,
+    /// UTC timestamp formatted as `YYYY-MM-DD HH:MM:SS`
+    pub created_at: String,
+}
+
+/// Query the submission log, newest first
+pub fn get_submission_log(conn: &Connection, filter: &HistoryFilter) -> SqlResult<Vec<LogEntry>> {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if let Some(ids) = &filter.source_ids {
+        let placeholders: Vec<String> = ids
+            .iter()
+            .map(|id| {
+                params.push(Box::new(*id));
+                format!("?{}", params.len())
+            })
+            .collect();
+        conditions.push(format!("l.source_id IN ({})", placeholders.join(", ")));
+    }
+    if let Some(pattern) = &filter.url_contains {
+        params.push(Box::new(pattern.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM submission_log_urls u WHERE u.log_id = l.id AND instr(u.url, ?{}) > 0)",
+            params.len()
+        ));
+    }
+    if let Some(since) = &filter.since {
+        params.push(Box::new(since.clone()));
+        conditions.push(format!("l.created_at >= CAST(strftime('%s', ?{}) AS INTEGER)", params.len()));
+    }
+    if let Some(until) = &filter.until {
+        params.push(Box::new(until.clone()));
+        conditions.push(format!("l.created_at < CAST(strftime('%s', ?{}, '+1 day') AS INTEGER)", params.len()));
+    }
+    match filter.status {
+        Some(StatusFilter::Success) => conditions.push("l.http_status BETWEEN 200 AND 299".to_string()),
+        Some(StatusFilter::Failed) => {
+            conditions.push("(l.http_status IS NULL OR l.http_status NOT BETWEEN 200 AND 299)".to_string())
+        }
+        Some(StatusFilter::Code(code)) => {
+            params.push(Box::new(code));
+            conditions.push(format!("l.http_status = ?{}", params.len()));
+        }
+        None => {}
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    params.push(Box::new(filter.limit as i64));
+    let sql = format!(
+        "SELECT l.id, l.source_id, l.endpoint, l.url_count, l.http_status, l.response_body, l.duration_ms, l.reason,
+                datetime(l.created_at, 'unixepoch'), l.host
+         FROM submission_log l {}
+         ORDER BY l.id DESC LIMIT ?{}",
+        where_clause,
+        params.len()
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let entries = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(LogEntry {
+                id: row.get(0)?,
+                source_id: row.get(1)?,
+                endpoint: row.get(2)?,
+                url_count: row.get(3)?,
+                http_status: row.get(4)?,
+                response_body: row.get(5)?,
+                duration_ms: row.get(6)?,
+                reason: row.get(7)?,
+                created_at: row.get(8)?,
+                host: row.get(9)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(entries)
+}
+
+/// Get the URLs (and their reasons) sent in a logged request
+pub fn get_log_urls(conn: &Connection, log_id: i64) -> SqlResult<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, reason FROM submission_log_urls WHERE log_id = ?1 ORDER BY rowid",
     )?;
+    let urls = stmt
+        .query_map([log_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(urls)
+}
+
+// ============================================================================
+// HTTP cache
+// ============================================================================
+
+/// A previously fetched feed or sitemap with its validators and contents
+#[derive(Debug, Clone, Default)]
+pub struct CachedDocument {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// URLs listed in the document
+    pub entries: Vec<UrlEntry>,
+    /// Sub-sitemaps listed in a sitemap index
+    pub sitemaps: Vec<String>,
+}
+
+/// Get the cached validators and contents of a document
+pub fn get_cached_document(conn: &Connection, url: &str) -> SqlResult<Option<CachedDocument>> {
+    let validators: Option<(Option<String>, Option<String>)> = conn
+        .query_row(
+            "SELECT etag, last_modified FROM http_cache WHERE url = ?1",
+            [url],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((etag, last_modified)) = validators else {
+        return Ok(None);
+    };
+
+    let mut document = CachedDocument {
+        etag,
+        last_modified,
+        ..Default::default()
+    };
+    let mut stmt = conn.prepare(
+        "SELECT kind, url, last_modified FROM http_cache_entries WHERE document_url = ?1 ORDER BY position",
+    )?;
+    let rows = stmt.query_map([url], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+    })?;
+    for row in rows {
+        let (kind, entry_url, date) = row?;
+        if kind == "sitemap" {
+            document.sitemaps.push(entry_url);
+        } else {
+            document.entries.push(UrlEntry { url: entry_url, date });
+        }
+    }
+
+    Ok(Some(document))
+}
+
+/// Store a document's validators and contents, replacing any previous version
+pub fn store_cached_document(conn: &Connection, url: &str, document: &CachedDocument) -> SqlResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM http_cache_entries WHERE document_url = ?1", [url])?;
+    tx.execute(
+        "INSERT INTO http_cache (url, etag, last_modified) VALUES (?1, ?2, ?3)
+         ON CONFLICT(url) DO UPDATE SET etag = ?2, last_modified = ?3, fetched_at = strftime('%s', 'now')",
+        rusqlite::params![url, document.etag, document.last_modified],
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO http_cache_entries (document_url, position, kind, url, last_modified)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let sitemaps = document.sitemaps.iter().map(|s| ("sitemap", s.as_str(), None));
+        let entries = document.entries.iter().map(|e| ("url", e.url.as_str(), e.date.as_deref()));
+        for (position, (kind, entry_url, date)) in sitemaps.chain(entries).enumerate() {
+            stmt.execute(rusqlite::params![url, position as i64, kind, entry_url, date])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Drop a document from the cache, e.g. when the server stopped sending validators
+pub fn forget_cached_document(conn: &Connection, url: &str) -> SqlResult<()> {
+    conn.execute("DELETE FROM http_cache_entries WHERE document_url = ?1", [url])?;
+    conn.execute("DELETE FROM http_cache WHERE url = ?1", [url])?;
     Ok(())
 }
 
@@ -328,48 +1576,70 @@ pub fn add_url_with_date_for_source(conn: &Connection, source_id: i64, url: &str
 // Database maintenance
 // ============================================================================
 
-pub fn clear_database() -> Result<(), Box<dyn std::error::Error>> {
-    let path = db_path()?;
+/// Size of the database file in bytes and how much of it is free pages that
+/// `VACUUM` would give back
+pub fn database_size(conn: &Connection) -> SqlResult<(u64, u64)> {
+    let pragma = |name: &str| conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0));
+    let page_size = pragma("page_size")? as u64;
+    Ok((pragma("page_count")? as u64 * page_size, pragma("freelist_count")? as u64 * page_size))
+}
+
+/// Rebuild the database file to reclaim the space of deleted rows
+pub fn vacuum(conn: &Connection) -> SqlResult<()> {
+    conn.execute_batch("VACUUM")
+}
 
-    println!(
+pub fn clear_database(path: &Path, yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    say!(
         "{} {}",
         "⚠ WARNING:".red().bold(),
-        "This will delete all stored URLs and sources from the database!".red()
+        "This will delete all stored URLs, sources, and submission history from the database!".red()
     );
-    println!(
+    say!(
         "{}",
         "This is a destructive operation and cannot be undone.".yellow()
     );
-    println!("Database path: {}\n", path.display().to_string().dimmed());
+    say!("Database path: {}\n", path.display().to_string().dimmed());
 
     if !path.exists() {
-        println!(
+        say!(
             "{} Database does not exist. Nothing to clear.",
             "ℹ".cyan().bold()
         );
         return Ok(());
     }
 
-    if Confirm::new()
-        .with_prompt("Are you sure you want to clear the database?")
-        .default(false)
-        .interact()?
+    if yes
+        || Confirm::new()
+            .with_prompt("Are you sure you want to clear the database?")
+            .default(false)
+            .interact()?
     {
-        let conn = Connection::open(&path)?;
+        let conn = init_db(path)?;
         conn.execute("DELETE FROM submitted_urls", [])?;
+        conn.execute("DELETE FROM url_endpoints", [])?;
+        conn.execute("DELETE FROM pending_submissions", [])?;
+        conn.execute("DELETE FROM source_endpoints", [])?;
+        conn.execute("DELETE FROM source_filters", [])?;
+        conn.execute("DELETE FROM source_hosts", [])?;
+        conn.execute("DELETE FROM key_checks", [])?;
+        conn.execute("DELETE FROM submission_log_urls", [])?;
+        conn.execute("DELETE FROM submission_log", [])?;
         conn.execute("DELETE FROM sources", [])?;
         conn.execute("DELETE FROM app_state", [])?;
+        conn.execute("DELETE FROM http_cache_entries", [])?;
+        conn.execute("DELETE FROM http_cache", [])?;
 
-        println!(
+        say!(
             "{} Database cleared. URLs, sources, and app state removed.",
             "✓".green().bold()
         );
-        println!(
+        say!(
             "{} The next run will be treated as a first run.",
             "ℹ".cyan().bold()
         );
     } else {
-        println!("{} Operation cancelled.", "ℹ".cyan().bold());
+        say!("{} Operation cancelled.", "ℹ".cyan().bold());
     }
 
     Ok(())
@@ -1,23 +1,28 @@
 //! Feed parsing for RSS, Atom, and JSON feeds
 
-/// Copyright (C) 2026 Andre Franca <andre@abf.li>
-///
-/// This program is free software: you can redistribute it and/or modify
-/// it under the terms of the GNU Affero General Public License as published by
-/// the Free Software Foundation, either version 3 of the License, or
-/// (at your option) any later version.
-///
-/// This program is distributed in the hope that it will be useful,
-/// but WITHOUT ANY WARRANTY; without even the implied warranty of
-/// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
-/// GNU Affero General Public License for more details.
-///
-/// You should have received a copy of the GNU Affero General Public License
-/// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::http_cache::{self, Fetched};
 use feed_rs::parser;
+use regex::Regex;
 use reqwest::blocking::Client;
+use rusqlite::Connection;
+use std::collections::HashMap;
 use std::time::Duration;
+use url::Url;
 
 /// Represents a URL entry with its associated date
 #[derive(Debug, Clone)]
@@ -29,7 +34,7 @@ pub struct UrlEntry {
     pub date: Option<String>,
 }
 
-pub fn fetch_feed_urls(feed_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
+pub fn fetch_feed_urls(conn: &Connection, feed_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
     let user_agent = format!(
         "{}/{} (+{})",
         env!("CARGO_PKG_NAME"),
@@ -42,7 +47,10 @@ pub fn fetch_feed_urls(feed_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::err
         .user_agent(user_agent)
         .build()?;
 
-    let response = client.get(feed_url).send()?;
+    let response = match http_cache::conditional_get(conn, &client, feed_url)? {
+        Fetched::NotModified(document) => return Ok(document.entries),
+        Fetched::Modified(response) => response,
+    };
 
     if !response.status().is_success() {
         return Err(format!(
@@ -52,6 +60,7 @@ pub fn fetch_feed_urls(feed_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::err
         .into());
     }
 
+    let mut document = http_cache::new_document(&response);
     let content = response.bytes()?;
 
     // feed-rs automatically detects RSS, Atom, or JSON Feed format
@@ -81,5 +90,124 @@ pub fn fetch_feed_urls(feed_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::err
         })
         .collect();
 
+    document.entries = entries.clone();
+    http_cache::remember(conn, feed_url, &document)?;
+
     Ok(entries)
 }
+
+/// Feed types advertised with `<link rel="alternate">`
+const FEED_MIME_TYPES: [&str; 3] = ["application/rss+xml", "application/atom+xml", "application/feed+json"];
+
+/// A feed advertised by an HTML page
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredFeed {
+    pub url: String,
+    pub mime_type: String,
+    pub title: Option<String>,
+}
+
+impl std::fmt::Display for DiscoveredFeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.mime_type.as_str() {
+            "application/atom+xml" => "Atom",
+            "application/feed+json" => "JSON Feed",
+            _ => "RSS",
+        };
+        match &self.title {
+            Some(title) => write!(f, "{} ({}) - {}", title, kind, self.url),
+            None => write!(f, "{} - {}", kind, self.url),
+        }
+    }
+}
+
+/// Find the feeds an HTML page advertises through
+/// `<link rel="alternate" type="application/rss+xml|atom+xml|feed+json" href="...">`,
+/// in document order. Relative `href`s are resolved against `page_url`.
+pub fn discover_feeds(html: &str, page_url: &str) -> Vec<DiscoveredFeed> {
+    let link_re = Regex::new(r"(?is)<link\b([^>]*)>").expect("valid regex");
+    let attr_re = Regex::new(r#"(?s)([a-zA-Z_:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).expect("valid regex");
+    let base = Url::parse(page_url).ok();
+    let mut feeds: Vec<DiscoveredFeed> = Vec::new();
+
+    for link in link_re.captures_iter(html) {
+        let attrs: HashMap<String, String> = attr_re
+            .captures_iter(&link[1])
+            .map(|c| {
+                let value = c.get(2).or(c.get(3)).or(c.get(4)).map_or("", |m| m.as_str());
+                (c[1].to_ascii_lowercase(), decode_html_entities(value.trim()))
+            })
+            .collect();
+
+        let is_alternate = attrs
+            .get("rel")
+            .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("alternate")));
+        let mime_type = attrs.get("type").map(|t| t.to_ascii_lowercase()).unwrap_or_default();
+        if !is_alternate || !FEED_MIME_TYPES.contains(&mime_type.as_str()) {
+            continue;
+        }
+
+        let Some(href) = attrs.get("href").filter(|h| !h.is_empty()) else {
+            continue;
+        };
+        let url = match &base {
+            Some(base) => base.join(href).map(String::from),
+            None => Url::parse(href).map(String::from),
+        };
+        let Ok(url) = url else {
+            continue;
+        };
+
+        if !feeds.iter().any(|f| f.url == url) {
+            feeds.push(DiscoveredFeed {
+                url,
+                mime_type,
+                title: attrs.get("title").filter(|t| !t.is_empty()).cloned(),
+            });
+        }
+    }
+
+    feeds
+}
+
+/// Decode the entities that commonly appear in HTML attribute values
+fn decode_html_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_feeds() {
+        let html = r#"<!DOCTYPE html>
+<html><head>
+  <link rel="stylesheet" href="/style.css">
+  <LINK REL="alternate" TYPE="application/rss+xml" TITLE="Blog" HREF="/feed.xml">
+  <link type='application/atom+xml' rel='alternate' href='https://example.com/atom.xml?a=1&amp;b=2' />
+  <link rel="alternate" type="application/feed+json" title="JSON" href="feed.json">
+  <link rel="alternate" type="text/html" hreflang="de" href="/de/">
+  <link rel="alternate" type="application/rss+xml" href="/feed.xml">
+</head><body></body></html>"#;
+
+        let feeds = discover_feeds(html, "https://example.com/blog/");
+        let urls: Vec<&str> = feeds.iter().map(|f| f.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/feed.xml",
+                "https://example.com/atom.xml?a=1&b=2",
+                "https://example.com/blog/feed.json",
+            ]
+        );
+        assert_eq!(feeds[0].title.as_deref(), Some("Blog"));
+        assert_eq!(feeds[1].mime_type, "application/atom+xml");
+    }
+}
@@ -0,0 +1,260 @@
+//! Per-source include/exclude rules applied to discovered URLs
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::feed::UrlEntry;
+use regex::Regex;
+use serde::Serialize;
+use url::Url;
+
+/// Prefix marking a pattern as a regular expression instead of a glob
+const REGEX_PREFIX: &str = "re:";
+
+/// Whether a rule keeps or drops the URLs it matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    Include,
+    Exclude,
+}
+
+impl RuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleKind::Include => "include",
+            RuleKind::Exclude => "exclude",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "include" => RuleKind::Include,
+            _ => RuleKind::Exclude,
+        }
+    }
+}
+
+/// A URL filter rule as stored for a source.
+///
+/// Patterns are globs where `*` matches any run of characters. A glob starting
+/// with `/` is matched against the URL's path and query, any other glob against
+/// the whole URL. Patterns prefixed with `re:` are regular expressions searched
+/// anywhere in the URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rule {
+    pub kind: RuleKind,
+    pub pattern: String,
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.kind.as_str(), self.pattern)
+    }
+}
+
+/// Compiled rules of a source
+pub struct Filter {
+    rules: Vec<(Rule, Matcher)>,
+}
+
+struct Matcher {
+    regex: Regex,
+    /// Match against path and query instead of the whole URL
+    path_only: bool,
+}
+
+/// How many URLs the rules of a source dropped
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FilterReport {
+    /// URLs that matched none of the include rules
+    pub not_included: usize,
+    /// URLs dropped by each exclude rule, credited to the first rule that matched
+    pub excluded: Vec<(String, usize)>,
+}
+
+impl FilterReport {
+    pub fn total(&self) -> usize {
+        self.not_included + self.excluded.iter().map(|(_, n)| n).sum::<usize>()
+    }
+}
+
+/// Check a pattern, returning a message that can be shown to the user if it is invalid
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    compile(pattern).map(|_| ())
+}
+
+fn compile(pattern: &str) -> Result<Matcher, String> {
+    if let Some(expr) = pattern.strip_prefix(REGEX_PREFIX) {
+        let regex = Regex::new(expr).map_err(|e| format!("invalid regex '{}': {}", expr, e))?;
+        return Ok(Matcher { regex, path_only: false });
+    }
+    if pattern.trim().is_empty() {
+        return Err("filter pattern must not be empty".to_string());
+    }
+
+    let glob = pattern
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+    let regex = Regex::new(&format!("^{}$", glob)).map_err(|e| e.to_string())?;
+    Ok(Matcher {
+        regex,
+        path_only: pattern.starts_with('/'),
+    })
+}
+
+impl Matcher {
+    fn is_match(&self, url: &str) -> bool {
+        if !self.path_only {
+            return self.regex.is_match(url);
+        }
+        match Url::parse(url) {
+            Ok(parsed) => {
+                let target = match parsed.query() {
+                    Some(query) => format!("{}?{}", parsed.path(), query),
+                    None => parsed.path().to_string(),
+                };
+                self.regex.is_match(&target)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl Filter {
+    pub fn new(rules: &[Rule]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|rule| compile(&rule.pattern).map(|m| (rule.clone(), m)))
+            .collect::<Result<_, _>>()?;
+        Ok(Filter { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether a single URL passes the rules: it matches an include rule (if
+    /// there are any) and no exclude rule
+    pub fn allows(&self, url: &str) -> bool {
+        let has_includes = self.rules.iter().any(|(rule, _)| rule.kind == RuleKind::Include);
+        let included = !has_includes
+            || self
+                .rules
+                .iter()
+                .any(|(rule, m)| rule.kind == RuleKind::Include && m.is_match(url));
+        included
+            && !self
+                .rules
+                .iter()
+                .any(|(rule, m)| rule.kind == RuleKind::Exclude && m.is_match(url))
+    }
+
+    /// Keep the entries that match an include rule (if there are any) and no exclude rule
+    pub fn apply(&self, entries: Vec<UrlEntry>) -> (Vec<UrlEntry>, FilterReport) {
+        let mut report = FilterReport {
+            not_included: 0,
+            excluded: self
+                .rules
+                .iter()
+                .filter(|(rule, _)| rule.kind == RuleKind::Exclude)
+                .map(|(rule, _)| (rule.pattern.clone(), 0))
+                .collect(),
+        };
+        if self.rules.is_empty() {
+            return (entries, report);
+        }
+
+        let has_includes = self.rules.iter().any(|(rule, _)| rule.kind == RuleKind::Include);
+        let mut kept = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let included = !has_includes
+                || self
+                    .rules
+                    .iter()
+                    .any(|(rule, m)| rule.kind == RuleKind::Include && m.is_match(&entry.url));
+            if !included {
+                report.not_included += 1;
+                continue;
+            }
+
+            let excluded_by = self
+                .rules
+                .iter()
+                .filter(|(rule, _)| rule.kind == RuleKind::Exclude)
+                .position(|(_, m)| m.is_match(&entry.url));
+            match excluded_by {
+                Some(index) => report.excluded[index].1 += 1,
+                None => kept.push(entry),
+            }
+        }
+
+        (kept, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: RuleKind, pattern: &str) -> Rule {
+        Rule { kind, pattern: pattern.to_string() }
+    }
+
+    #[test]
+    fn test_apply_filters() {
+        let entries: Vec<UrlEntry> = [
+            "https://example.com/blog/post-1/",
+            "https://example.com/blog/page/2/",
+            "https://example.com/blog/post-2/?utm_source=feed",
+            "https://example.com/tag/rust/",
+            "https://example.com/about/",
+        ]
+        .iter()
+        .map(|url| UrlEntry { url: url.to_string(), date: None })
+        .collect();
+
+        let filter = Filter::new(&[
+            rule(RuleKind::Include, "/blog/*"),
+            rule(RuleKind::Include, "/tag/*"),
+            rule(RuleKind::Exclude, "*?utm_*"),
+            rule(RuleKind::Exclude, r"re:/page/\d+/"),
+            rule(RuleKind::Exclude, "https://example.com/tag/*"),
+        ])
+        .unwrap();
+        let (kept, report) = filter.apply(entries);
+
+        let urls: Vec<&str> = kept.iter().map(|e| e.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.com/blog/post-1/"]);
+        assert_eq!(report.not_included, 1);
+        assert_eq!(
+            report.excluded,
+            vec![
+                ("*?utm_*".to_string(), 1),
+                (r"re:/page/\d+/".to_string(), 1),
+                ("https://example.com/tag/*".to_string(), 1),
+            ]
+        );
+        assert_eq!(report.total(), 4);
+        assert!(filter.allows("https://example.com/blog/post-3/"));
+        assert!(!filter.allows("https://example.com/blog/page/3/"));
+        assert!(!filter.allows("https://example.com/about/"));
+
+        assert!(validate_pattern("re:(unclosed").is_err());
+    }
+}
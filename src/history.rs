@@ -0,0 +1,133 @@
+//! Submission history display
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, HistoryFilter, StatusFilter};
+use crate::output::{self, say, Record};
+use colored::*;
+use std::path::Path;
+
+/// Default number of log entries shown by `--history`
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// Maximum length of a response body shown in the history
+const MAX_BODY_LEN: usize = 200;
+
+/// Parse a `--status` value: `ok`, `failed`, or an HTTP status code
+pub fn parse_status_filter(value: &str) -> Result<StatusFilter, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "ok" | "success" => Ok(StatusFilter::Success),
+        "failed" | "error" => Ok(StatusFilter::Failed),
+        other => other
+            .parse::<u16>()
+            .map(StatusFilter::Code)
+            .map_err(|_| format!("expected 'ok', 'failed', or an HTTP status code, got '{}'", value)),
+    }
+}
+
+/// Show logged IndexNow requests matching the filter
+pub fn show_history(db_path: &Path, filter: &HistoryFilter) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = db::init_db(db_path)?;
+    let entries = db::get_submission_log(&conn, filter)?;
+
+    if output::is_structured() {
+        for entry in &entries {
+            let urls = db::get_log_urls(&conn, entry.id)?;
+            output::emit(&Record::History {
+                id: entry.id,
+                source_id: entry.source_id,
+                endpoint: &entry.endpoint,
+                host: entry.host.as_deref(),
+                url_count: entry.url_count,
+                status: entry.http_status,
+                response_body: entry.response_body.as_deref(),
+                duration_ms: entry.duration_ms,
+                reason: &entry.reason,
+                created_at: &entry.created_at,
+                urls: urls.iter().map(|(url, _)| url.as_str()).collect(),
+            });
+        }
+        return Ok(());
+    }
+
+    say!(
+        "{} Submission History",
+        "═".repeat(40).blue().bold()
+    );
+
+    if entries.is_empty() {
+        say!("\n{} No submissions match the given filters.", "ℹ".cyan().bold());
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let status = match entry.http_status {
+            Some(code @ 200..=299) => code.to_string().green().bold(),
+            Some(code) => code.to_string().red().bold(),
+            None => "no response".red().bold(),
+        };
+        let source = entry
+            .source_id
+            .map(|id| format!("[ID {}]", id))
+            .unwrap_or_else(|| "[ad-hoc]".to_string());
+
+        let host = entry
+            .host
+            .as_deref()
+            .map(|h| format!("for {} ", h).dimmed().to_string())
+            .unwrap_or_default();
+
+        say!(
+            "\n  {} {} {} {} {}{} {} URL(s), {}, {} ms",
+            format!("#{}", entry.id).dimmed(),
+            entry.created_at,
+            source.bold(),
+            entry.endpoint.cyan(),
+            host,
+            status,
+            entry.url_count,
+            entry.reason,
+            entry.duration_ms
+        );
+
+        if let Some(body) = entry.response_body.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+            let body: String = body.chars().take(MAX_BODY_LEN).collect();
+            say!("     {} {}", "Response:".dimmed(), body.dimmed());
+        }
+
+        let urls = db::get_log_urls(&conn, entry.id)?;
+        let shown: Vec<&(String, String)> = match &filter.url_contains {
+            Some(pattern) => urls.iter().filter(|(url, _)| url.contains(pattern.as_str())).collect(),
+            None => urls.iter().take(5).collect(),
+        };
+        for (url, reason) in &shown {
+            say!("     • {} {}", url, format!("({})", reason).dimmed());
+        }
+        if filter.url_contains.is_none() && urls.len() > shown.len() {
+            say!("     {} ... and {} more", "".dimmed(), urls.len() - shown.len());
+        }
+    }
+
+    if entries.len() == filter.limit {
+        say!(
+            "\n{} Showing the {} most recent entries. Use '--limit' to see more.",
+            "ℹ".cyan().bold(),
+            filter.limit
+        );
+    }
+
+    Ok(())
+}
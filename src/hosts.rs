@@ -0,0 +1,171 @@
+//! Host validation keeping off-host URLs out of IndexNow requests
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::SourceHost;
+use crate::keyfile;
+use crate::output::say;
+use colored::*;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Host of a URL, lowercased, or `None` if it has none
+pub fn url_host(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_end_matches('.').to_ascii_lowercase()))
+}
+
+/// Whether a URL belongs to the configured host. IndexNow treats `www.` and
+/// the apex as different hosts, so they don't match each other.
+pub fn is_on_host(url: &str, host: &str) -> bool {
+    url_host(url).is_some_and(|h| h == normalize_host(host))
+}
+
+/// Parse an `--extra-host` value: `HOST=KEY` or `HOST=KEY=KEY_LOCATION`
+pub fn parse_extra_host(value: &str) -> Result<SourceHost, String> {
+    let mut parts = value.splitn(3, '=');
+    let host = parts.next().unwrap_or_default();
+    let key = parts.next().unwrap_or_default();
+    if host.trim().is_empty() || key.trim().is_empty() {
+        return Err(format!("expected HOST=KEY or HOST=KEY=KEY_LOCATION, got '{}'", value));
+    }
+    extra_host(host, key, parts.next())
+}
+
+/// Validate an additional host of a source along with its key and key location
+pub fn extra_host(host: &str, key: &str, key_location: Option<&str>) -> Result<SourceHost, String> {
+    let host = host.trim();
+    let key = key.trim();
+    let key_location = key_location.map(str::trim).filter(|l| !l.is_empty());
+    if host.is_empty() || key.is_empty() {
+        return Err("extra hosts need both a host and a key".to_string());
+    }
+    if host.contains('/') || host.contains(':') {
+        return Err(format!("'{}' is not a host name; give it without scheme, port or path", host));
+    }
+    keyfile::validate_key(key).map_err(|e| format!("{}: {}", host, e))?;
+    if let Some(location) = key_location {
+        validate_key_location(location, host)?;
+    }
+    Ok(SourceHost {
+        host: normalize_host(host),
+        api_key: key.to_string(),
+        key_location: key_location.map(String::from),
+    })
+}
+
+/// Check that a key location is an absolute http(s) URL on the host it proves.
+/// IndexNow ignores key files on other hosts.
+pub fn validate_key_location(location: &str, host: &str) -> Result<(), String> {
+    match Url::parse(location) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        _ => return Err(format!("key location must be an http(s) URL, got '{}'", location)),
+    }
+    if !is_on_host(location, host) {
+        return Err(format!("key location {} is not on {}", location, normalize_host(host)));
+    }
+    Ok(())
+}
+
+/// Whether a URL is on one of the hosts a source submits for
+pub fn is_allowed(url: &str, hosts: &[SourceHost]) -> bool {
+    hosts.iter().any(|h| is_on_host(url, &h.host))
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Whether two hosts differ only by a leading `www.`
+fn is_www_variant(a: &str, b: &str) -> bool {
+    a.strip_prefix("www.") == Some(b) || b.strip_prefix("www.") == Some(a)
+}
+
+/// Print the URLs that were kept out of a submission because they are on none
+/// of the source's hosts, grouped by the host they are on. `listed` limits how
+/// many URLs are shown per host.
+pub fn report_off_host(hosts: &[SourceHost], urls: &[&str], listed: usize, dry_run: bool) {
+    if urls.is_empty() {
+        return;
+    }
+    let allowed: Vec<String> = hosts.iter().map(|h| normalize_host(&h.host)).collect();
+    let mut by_host: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for url in urls {
+        let url_host = url_host(url).unwrap_or_else(|| "(no host)".to_string());
+        by_host.entry(url_host).or_default().push(url);
+    }
+
+    say!(
+        "  {} {} URL(s) are not on {} and {} quarantined instead of submitted:",
+        "⚠".yellow().bold(),
+        urls.len(),
+        allowed.join(", ").cyan(),
+        if dry_run { "would be" } else { "were" }
+    );
+    for (url_host, urls) in &by_host {
+        say!("    • {} on {}", urls.len(), url_host);
+        for url in urls.iter().take(listed) {
+            say!("        {}", url.dimmed());
+        }
+        if urls.len() > listed {
+            say!("        {}", format!("... and {} more", urls.len() - listed).dimmed());
+        }
+        if let Some(host) = allowed.iter().find(|h| is_www_variant(url_host, h)) {
+            say!(
+                "      {} {} and {} are different hosts to IndexNow. If {} is canonical, set it as the host with --config --host {}.",
+                "→".blue().bold(),
+                url_host,
+                host,
+                url_host,
+                url_host
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_on_host() {
+        assert!(is_on_host("https://Example.com/post", "example.com"));
+        assert!(is_on_host("http://example.com:8080/post", "EXAMPLE.COM."));
+        assert!(!is_on_host("https://www.example.com/post", "example.com"));
+        assert!(!is_on_host("https://example.com/post", "www.example.com"));
+        assert!(!is_on_host("https://cdn.other.net/image.png", "example.com"));
+        assert!(!is_on_host("mailto:someone@example.com", "example.com"));
+
+        let hosts = [
+            SourceHost { host: "example.com".to_string(), api_key: "k1".to_string(), key_location: None },
+            parse_extra_host("Blog.Example.com=blogkey2=https://blog.example.com/.well-known/blogkey2.txt").unwrap(),
+        ];
+        assert_eq!(hosts[1].host, "blog.example.com");
+        assert!(is_allowed("https://blog.example.com/post", &hosts));
+        assert!(!is_allowed("https://shop.example.com/post", &hosts));
+        assert!(parse_extra_host("blog.example.com").is_err());
+        assert!(parse_extra_host("https://blog.example.com=blogkey2").is_err());
+        assert!(parse_extra_host("blog.example.com=blogkey2=/blogkey2.txt").is_err());
+        assert!(parse_extra_host("blog.example.com=blogkey2=https://example.com/blogkey2.txt").is_err());
+        assert!(parse_extra_host("blog.example.com=k2").unwrap_err().contains("8 to 128 characters"));
+        assert!(parse_extra_host("blog.example.com=blog_key_2").unwrap_err().contains("may only contain"));
+        assert!(validate_key_location("https://example.com/.well-known/k1.txt", "Example.com").is_ok());
+
+        assert!(is_www_variant("www.example.com", "example.com"));
+        assert!(!is_www_variant("blog.example.com", "example.com"));
+    }
+}
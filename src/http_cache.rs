@@ -0,0 +1,82 @@
+//! Conditional GET for feeds and sitemaps using stored ETag / Last-Modified validators
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, CachedDocument};
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use rusqlite::Connection;
+
+/// Result of a conditional GET
+pub enum Fetched {
+    /// The document is new or changed; the response body has to be parsed
+    Modified(Response),
+    /// The server answered 304 Not Modified; the cached contents are still current
+    NotModified(CachedDocument),
+}
+
+/// GET a document, sending the validators stored from the previous fetch
+pub fn conditional_get(conn: &Connection, client: &Client, url: &str) -> Result<Fetched, Box<dyn std::error::Error>> {
+    let cached = db::get_cached_document(conn, url)?;
+
+    let mut request = client.get(url);
+    if let Some(document) = &cached {
+        if let Some(etag) = &document.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &document.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send()?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return match cached {
+            Some(document) => Ok(Fetched::NotModified(document)),
+            None => Err(format!("{} answered 304 Not Modified to an unconditional request", url).into()),
+        };
+    }
+
+    Ok(Fetched::Modified(response))
+}
+
+/// Start a cache record holding the validators of a fresh response
+pub fn new_document(response: &Response) -> CachedDocument {
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+
+    CachedDocument {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        ..Default::default()
+    }
+}
+
+/// Store a parsed document for the next conditional GET. Documents served
+/// without validators can never be answered with a 304, so they are not kept.
+pub fn remember(conn: &Connection, url: &str, document: &CachedDocument) -> rusqlite::Result<()> {
+    if document.etag.is_none() && document.last_modified.is_none() {
+        db::forget_cached_document(conn, url)
+    } else {
+        db::store_cached_document(conn, url, document)
+    }
+}
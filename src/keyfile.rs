@@ -0,0 +1,474 @@
+//! Preflight check that the IndexNow key file is served where search engines look for it
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, Source, SourceHost};
+use crate::hosts;
+use crate::output::{self, say, Record};
+use colored::*;
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::redirect::Policy;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+/// Redirects followed before giving up on a key file
+const MAX_REDIRECTS: usize = 5;
+
+/// Shortest and longest key the IndexNow protocol allows
+pub const MIN_KEY_LENGTH: usize = 8;
+pub const MAX_KEY_LENGTH: usize = 128;
+
+/// Length of generated keys, as produced by `openssl rand -hex 16`
+pub const DEFAULT_KEY_LENGTH: usize = 32;
+
+/// Check a key against the IndexNow rules: 8 to 128 characters of `a-z`, `A-Z`, `0-9` and `-`
+pub fn validate_key(key: &str) -> Result<(), String> {
+    let length = key.chars().count();
+    if !(MIN_KEY_LENGTH..=MAX_KEY_LENGTH).contains(&length) {
+        return Err(format!(
+            "key must be {} to {} characters long, got {}",
+            MIN_KEY_LENGTH, MAX_KEY_LENGTH, length
+        ));
+    }
+    if let Some(c) = key.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '-')) {
+        return Err(format!("key may only contain letters, digits and '-', found '{}'", c));
+    }
+    Ok(())
+}
+
+/// Generate a random lowercase hex key of the given length
+pub fn generate_key(length: usize) -> Result<String, Box<dyn std::error::Error>> {
+    let mut bytes = vec![0u8; length.div_ceil(2)];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate a key: {}", e))?;
+    let mut key: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    key.truncate(length);
+    validate_key(&key)?;
+    Ok(key)
+}
+
+/// Write `{key}.txt` containing only the key into an existing directory
+pub fn write_key_file(dir: &Path, key: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()).into());
+    }
+    let path = dir.join(format!("{}.txt", key));
+    std::fs::write(&path, key).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Generate a key, optionally writing its key file into `dir` and making it
+/// the key of source `attach_to`
+pub fn keygen(
+    db_path: &Path,
+    length: usize,
+    dir: Option<&Path>,
+    attach_to: Option<i64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Look the source up first so a wrong ID leaves no key file behind
+    let target = match attach_to {
+        Some(id) => {
+            let conn = db::init_db(db_path)?;
+            let source = db::get_all_sources(&conn)?
+                .into_iter()
+                .find(|s| s.id == id)
+                .ok_or_else(|| format!("No source with ID {}. Run '{} --list' to see available sources.", id, env!("CARGO_PKG_NAME")))?;
+            Some((conn, source))
+        }
+        None => None,
+    };
+
+    let key = generate_key(length)?;
+    let file = dir.map(|dir| write_key_file(dir, &key)).transpose()?;
+    let source = match target {
+        Some((conn, source)) => {
+            db::update_source(&conn, source.id, &source.source_type, &source.source_url, &key, &source.host, &source.endpoints)?;
+            Some(source)
+        }
+        None => None,
+    };
+
+    output::emit(&Record::Key {
+        key: &key,
+        file: file.as_deref().map(|p| p.to_string_lossy()).as_deref(),
+        source_id: attach_to,
+    });
+    say!("{} Generated key: {}", "✓".green().bold(), key.bold());
+    if let Some(path) = &file {
+        say!("{} Wrote {}", "✓".green().bold(), path.display());
+    }
+
+    match &source {
+        Some(source) => {
+            say!(
+                "{} Source {} now uses the new key.",
+                "✓".green().bold(),
+                source.id
+            );
+            match &source.key_location {
+                Some(location) => say!(
+                    "{} The source expects its key file at {}. Update it with '{} --config --id {} --key-location <URL>'.",
+                    "⚠".yellow().bold(),
+                    location,
+                    env!("CARGO_PKG_NAME"),
+                    source.id
+                ),
+                None => say!(
+                    "{} Publish the key file at https://{}/{}.txt, then check it with '{} --verify -e {}'.",
+                    "→".blue().bold(),
+                    source.host,
+                    key,
+                    env!("CARGO_PKG_NAME"),
+                    source.id
+                ),
+            }
+        }
+        None => say!(
+            "{} Publish the key file at https://<your host>/{}.txt and use the key with --key.",
+            "→".blue().bold(),
+            key
+        ),
+    }
+    Ok(())
+}
+
+/// Outcome of fetching the key file of one host
+#[derive(Debug)]
+pub struct KeyCheck {
+    pub host: String,
+    /// Where the key file was looked for
+    pub url: String,
+    /// HTTP status of the last response, `None` if there was none
+    pub status: Option<u16>,
+    /// Every redirect target, in order
+    pub redirects: Vec<String>,
+    pub content_type: Option<String>,
+    /// Issues that make search engines reject the key; they block submission
+    pub problems: Vec<String>,
+    /// Issues worth fixing that don't block submission
+    pub warnings: Vec<String>,
+}
+
+impl KeyCheck {
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Where search engines look for the key of a host
+pub fn key_file_url(host: &SourceHost) -> String {
+    match &host.key_location {
+        Some(location) => location.clone(),
+        None => format!("https://{}/{}.txt", host.host, host.api_key),
+    }
+}
+
+/// Check the key files of a source before submitting. A host that passed today
+/// is not fetched again. Returns whether every host passed; failures are printed
+/// with what to fix. A dry run doesn't update the cache.
+pub fn preflight(conn: &Connection, source: &Source, dry_run: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut client = None;
+    let mut passed = true;
+
+    for host in source.hosts() {
+        if db::key_check_passed_today(conn, source.id, &host)? {
+            continue;
+        }
+        let client = match &client {
+            Some(client) => client,
+            None => client.insert(build_client()?),
+        };
+        let check = check_host(client, &host);
+        emit(source.id, &check);
+        if !dry_run {
+            db::record_key_check(conn, source.id, &host, check.passed())?;
+        }
+
+        if check.passed() && check.warnings.is_empty() {
+            say!(
+                "  {} Key file verified for {}.",
+                "✓".green().bold(),
+                check.host
+            );
+        } else {
+            print_check(&check);
+        }
+        passed &= check.passed();
+    }
+
+    if !passed {
+        say!(
+            "  {} Submissions for source {} {} blocked. Fix the key file, then check it with '{} --verify -e {}'.",
+            "→".blue().bold(),
+            source.id,
+            if dry_run { "would be" } else { "are" },
+            env!("CARGO_PKG_NAME"),
+            source.id
+        );
+    }
+    Ok(passed)
+}
+
+/// Check the key files of the given sources, ignoring earlier results, and
+/// cache the outcome. Returns the number of hosts that failed.
+pub fn verify(conn: &Connection, sources: &[Source]) -> Result<usize, Box<dyn std::error::Error>> {
+    say!(
+        "{} Key Files",
+        "═".repeat(40).blue().bold()
+    );
+
+    let client = build_client()?;
+    let (mut checked, mut failed) = (0, 0);
+    for source in sources {
+        say!("\n  ID {} {}", source.id.to_string().bold(), source.source_url.dimmed());
+        for host in source.hosts() {
+            let check = check_host(&client, &host);
+            emit(source.id, &check);
+            db::record_key_check(conn, source.id, &host, check.passed())?;
+            print_check(&check);
+            checked += 1;
+            if !check.passed() {
+                failed += 1;
+            }
+        }
+    }
+
+    say!();
+    if failed == 0 {
+        say!("{} All {} key file(s) are valid.", "✓".green().bold(), checked);
+    } else {
+        say!(
+            "{} {} of {} key file(s) failed. Submissions for those sources are blocked until they pass.",
+            "✗".red().bold(),
+            failed,
+            checked
+        );
+    }
+    Ok(failed)
+}
+
+/// Fetch the key file of a host, following redirects by hand so they can be reported
+pub fn check_host(client: &Client, host: &SourceHost) -> KeyCheck {
+    let mut check = KeyCheck {
+        host: host.host.clone(),
+        url: key_file_url(host),
+        status: None,
+        redirects: Vec::new(),
+        content_type: None,
+        problems: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let mut url = check.url.clone();
+    let response = loop {
+        let response = match client.get(&url).send() {
+            Ok(response) => response,
+            Err(e) => {
+                check.problems.push(format!("could not fetch {}: {}", url, e));
+                return check;
+            }
+        };
+        check.status = Some(response.status().as_u16());
+        if !response.status().is_redirection() {
+            break response;
+        }
+
+        let target = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|location| Url::parse(&url).ok()?.join(location).ok());
+        let Some(target) = target else {
+            check.problems.push(format!("HTTP {} redirect without a valid Location header", response.status().as_u16()));
+            return check;
+        };
+        check.redirects.push(target.to_string());
+        if check.redirects.len() > MAX_REDIRECTS {
+            check.problems.push(format!("more than {} redirects", MAX_REDIRECTS));
+            return check;
+        }
+        url = target.to_string();
+    };
+
+    if let Some(last) = check.redirects.last() {
+        if hosts::is_on_host(last, &host.host) {
+            check.warnings.push(format!(
+                "redirected to {}; some search engines don't follow redirects for key files",
+                last
+            ));
+        } else {
+            check.problems.push(format!(
+                "redirected to {}, which is not on {}; search engines only accept key files on the host itself",
+                last, host.host
+            ));
+        }
+    }
+
+    if !response.status().is_success() {
+        check.problems.push(format!("HTTP {} instead of 200", response.status().as_u16()));
+        return check;
+    }
+
+    check.content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+    match check.content_type.as_deref() {
+        Some(ct) if ct.to_ascii_lowercase().starts_with("text/plain") => {}
+        Some(ct) => check.warnings.push(format!("served as {}, expected text/plain", ct)),
+        None => check.warnings.push("served without a Content-Type, expected text/plain".to_string()),
+    }
+
+    match response.text() {
+        Ok(body) => match compare_body(&body, &host.api_key) {
+            Ok(warnings) => check.warnings.extend(warnings),
+            Err(problem) => check.problems.push(problem),
+        },
+        Err(e) => check.problems.push(format!("could not read the body: {}", e)),
+    }
+    check
+}
+
+/// Compare a key file body with the key. Whitespace or a byte order mark around
+/// the key are reported as warnings since engines usually trim them; anything
+/// else is an error.
+fn compare_body(body: &str, key: &str) -> Result<Vec<String>, String> {
+    if body == key {
+        return Ok(Vec::new());
+    }
+
+    let without_bom = body.strip_prefix('\u{feff}').unwrap_or(body);
+    let trimmed = without_bom.trim();
+    if trimmed == key {
+        let mut warnings = Vec::new();
+        if without_bom.len() != body.len() {
+            warnings.push("the file starts with a UTF-8 byte order mark".to_string());
+        }
+        if without_bom.trim_start().len() != without_bom.len() {
+            warnings.push("the key has leading whitespace".to_string());
+        }
+        let trailing = &without_bom[without_bom.trim_end().len()..];
+        if trailing == "\n" || trailing == "\r\n" {
+            warnings.push("the key is followed by a newline".to_string());
+        } else if !trailing.is_empty() {
+            warnings.push(format!("the key is followed by {} whitespace character(s)", trailing.chars().count()));
+        }
+        return Ok(warnings);
+    }
+
+    if trimmed.eq_ignore_ascii_case(key) {
+        return Err("the file contains the key in different letter case".to_string());
+    }
+    if trimmed.contains(key) {
+        return Err(format!(
+            "the file contains the key along with other text ({} bytes); it must contain only the key",
+            body.len()
+        ));
+    }
+    if trimmed.is_empty() {
+        return Err("the file is empty".to_string());
+    }
+
+    let preview: String = trimmed.chars().take(40).map(|c| if c.is_control() { ' ' } else { c }).collect();
+    let hint = if trimmed.starts_with('<') {
+        " (an HTML page, probably a catch-all route or error page)"
+    } else {
+        ""
+    };
+    Err(format!("the file does not contain the key: starts with '{}'{}", preview, hint))
+}
+
+fn print_check(check: &KeyCheck) {
+    let mark = if check.passed() { "✓".green().bold() } else { "✗".red().bold() };
+    say!("  {} {}: {}", mark, check.host, check.url.dimmed());
+    for problem in &check.problems {
+        say!("      {} {}", "✗".red(), problem);
+    }
+    for warning in &check.warnings {
+        say!("      {} {}", "⚠".yellow(), warning);
+    }
+    if !check.passed() {
+        say!(
+            "      {} Serve a text/plain file at {} that contains only the key, or set --key-location to where it is.",
+            "→".blue().bold(),
+            check.url
+        );
+    }
+}
+
+fn emit(source_id: i64, check: &KeyCheck) {
+    output::emit(&Record::KeyCheck {
+        source_id,
+        host: &check.host,
+        url: &check.url,
+        status: check.status,
+        redirects: check.redirects.iter().map(String::as_str).collect(),
+        content_type: check.content_type.as_deref(),
+        passed: check.passed(),
+        problems: &check.problems,
+        warnings: &check.warnings,
+    });
+}
+
+fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
+    Ok(Client::builder()
+        .timeout(Duration::from_secs(15))
+        .redirect(Policy::none())
+        .user_agent(format!(
+            "{}/{} (+{})",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_REPOSITORY")
+        ))
+        .build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_key() {
+        let key = generate_key(DEFAULT_KEY_LENGTH).unwrap();
+        assert_eq!(key.len(), DEFAULT_KEY_LENGTH);
+        assert!(validate_key(&key).is_ok());
+        assert_eq!(generate_key(9).unwrap().len(), 9);
+        assert_ne!(generate_key(MIN_KEY_LENGTH).unwrap(), generate_key(MIN_KEY_LENGTH).unwrap());
+
+        assert!(validate_key("abc-DEF-123").is_ok());
+        assert!(validate_key("short").is_err());
+        assert!(validate_key(&"a".repeat(MAX_KEY_LENGTH + 1)).is_err());
+        assert!(validate_key("has_underscore").unwrap_err().contains("'_'"));
+    }
+
+    #[test]
+    fn test_compare_body() {
+        let key = "a1b2c3d4e5f6";
+        assert_eq!(compare_body(key, key), Ok(Vec::new()));
+        assert_eq!(
+            compare_body("a1b2c3d4e5f6\n", key),
+            Ok(vec!["the key is followed by a newline".to_string()])
+        );
+        assert_eq!(compare_body("\u{feff} a1b2c3d4e5f6", key).unwrap().len(), 2);
+        assert!(compare_body("A1B2C3D4E5F6", key).unwrap_err().contains("letter case"));
+        assert!(compare_body("key: a1b2c3d4e5f6", key).unwrap_err().contains("only the key"));
+        assert!(compare_body("<!DOCTYPE html><html>", key).unwrap_err().contains("HTML page"));
+        assert!(compare_body("", key).unwrap_err().contains("empty"));
+    }
+}
@@ -1,30 +1,52 @@
-/// Copyright (C) 2026 Andre Franca <andre@abf.li>
-///
-/// This program is free software: you can redistribute it and/or modify
-/// it under the terms of the GNU Affero General Public License as published by
-/// the Free Software Foundation, either version 3 of the License, or
-/// (at your option) any later version.
-///
-/// This program is distributed in the hope that it will be useful,
-/// but WITHOUT ANY WARRANTY; without even the implied warranty of
-/// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
-/// GNU Affero General Public License for more details.
-///
-/// You should have received a copy of the GNU Affero General Public License
-/// along with this program.  If not, see <https://www.gnu.org/licenses/>.
-
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod adhoc;
 mod config;
 mod db;
 mod feed;
+mod filter;
+mod history;
+mod hosts;
+mod http_cache;
+mod keyfile;
+mod maintenance;
+mod manage;
+mod normalize;
+mod output;
+mod queue;
+mod removals;
+mod resubmit;
+mod robots;
 mod sitemap;
 mod submit;
+mod sync;
+mod watch;
 
 use clap::Parser;
 use colored::*;
 use config::SourceType;
+use db::UrlStatus;
 use dialoguer::{Confirm, Input};
 use feed::UrlEntry;
+use normalize::TrailingSlash;
+use output::{say, OutputFormat, Record};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
 use submit::{SubmitEntry, SubmitReason};
 
 /// IndexNow RSS/Atom/JSON/Sitemap feed submitter
@@ -33,6 +55,10 @@ use submit::{SubmitEntry, SubmitReason};
 #[command(disable_version_flag = true)]
 #[command(disable_help_flag = true)]
 #[command(about, long_about = None)]
+// Destructive commands run one at a time, never silently dropping the others
+#[command(group = clap::ArgGroup::new("maintenance")
+    .args(["clear_db", "reset", "prune", "forget", "vacuum", "resubmit"])
+    .multiple(false))]
 struct Cli {
     /// Edit configuration (API key, host, search engine)
     #[arg(short, long)]
@@ -62,6 +88,26 @@ struct Cli {
     #[arg(long)]
     clear_db: bool,
 
+    /// Forget every tracked URL of the sources given with -e, so their next run is a first run
+    #[arg(long)]
+    reset: bool,
+
+    /// Forget URLs their source hasn't listed for this many days (all sources, or those given with -e)
+    #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(u32).range(1..))]
+    prune: Option<u32>,
+
+    /// Forget URLs matching this glob or re:regex (all sources, or those given with -e)
+    #[arg(long, value_name = "PATTERN")]
+    forget: Option<String>,
+
+    /// Rebuild the database file to reclaim the space of deleted rows
+    #[arg(long)]
+    vacuum: bool,
+
+    /// Do not ask before --clear-db, --reset, --prune, --forget or --vacuum
+    #[arg(long)]
+    yes: bool,
+
     /// Dry run - show URLs that would be submitted without actually submitting
     #[arg(short, long)]
     dry_run: bool,
@@ -70,6 +116,179 @@ struct Cli {
     #[arg(short, long)]
     unattended: bool,
 
+    /// Stay resident and poll each source on its own interval (unattended)
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Poll interval for --add/--config (e.g. 15m, 2h), or the default for --watch
+    #[arg(long, value_name = "INTERVAL", value_parser = watch::parse_interval)]
+    interval: Option<i64>,
+
+    /// Source to edit or remove without prompting, or whose settings --submit uses
+    #[arg(long, visible_alias = "source", value_name = "ID")]
+    id: Option<i64>,
+
+    /// Source type for --add/--config: feed, sitemap or robots
+    #[arg(long = "type", value_name = "TYPE")]
+    source_type: Option<SourceType>,
+
+    /// IndexNow key for --add/--config
+    #[arg(long, value_name = "KEY")]
+    key: Option<String>,
+
+    /// Host (your domain) for --add/--config
+    #[arg(long, value_name = "HOST")]
+    host: Option<String>,
+
+    /// URL of the key file if it is not at https://HOST/KEY.txt, for --add/--config
+    #[arg(long, value_name = "URL")]
+    key_location: Option<String>,
+
+    /// Expect the key file at the host root again, for --config
+    #[arg(long, conflicts_with = "key_location")]
+    no_key_location: bool,
+
+    /// Comma-separated search engine hosts for --add/--config
+    #[arg(long, value_name = "HOSTS")]
+    engine: Option<String>,
+
+    /// Only submit URLs matching this glob or re:regex (repeatable, for --add/--config)
+    #[arg(long, value_name = "PATTERN")]
+    include: Vec<String>,
+
+    /// Never submit URLs matching this glob or re:regex (repeatable, for --add/--config)
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Remove all filter rules of the source edited with --config
+    #[arg(long)]
+    no_filters: bool,
+
+    /// Another host the source lists URLs for, as HOST=KEY[=KEY_LOCATION] (repeatable, for --add/--config)
+    #[arg(long, value_name = "HOST=KEY", value_parser = hosts::parse_extra_host)]
+    extra_host: Vec<db::SourceHost>,
+
+    /// Remove all extra hosts of the source edited with --config
+    #[arg(long)]
+    no_extra_hosts: bool,
+
+    /// Trailing slash policy for --add/--config: keep, add or remove
+    #[arg(long, value_name = "POLICY")]
+    trailing_slash: Option<TrailingSlash>,
+
+    /// Drop utm_* and other tracking parameters from URLs (true/false, for --add/--config)
+    #[arg(long, value_name = "BOOL")]
+    strip_tracking: Option<bool>,
+
+    /// Sort URL query parameters by name (true/false, for --add/--config)
+    #[arg(long, value_name = "BOOL")]
+    sort_query: Option<bool>,
+
+    /// Submit URLs missing from the source this long (e.g. 7d) as removed, for --add/--config
+    #[arg(long, value_name = "INTERVAL", value_parser = watch::parse_interval)]
+    removal_grace: Option<i64>,
+
+    /// Stop submitting URLs that disappear from the source, for --config
+    #[arg(long, conflicts_with = "removal_grace")]
+    no_removals: bool,
+
+    /// Only submit missing URLs that answer 404 or 410 (true/false, for --add/--config)
+    #[arg(long, value_name = "BOOL")]
+    probe_removals: Option<bool>,
+
+    /// Do not check that the source URL is reachable when adding or editing
+    #[arg(long)]
+    no_validate: bool,
+
+    /// Reconcile sources with a TOML file (shows the plan; -d to stop there, -u to skip confirmation)
+    #[arg(long, value_name = "FILE")]
+    apply: Option<PathBuf>,
+
+    /// Show queued and dead-lettered submissions
+    #[arg(long)]
+    queue: bool,
+
+    /// Check that the IndexNow key file of each source is served correctly
+    #[arg(long)]
+    verify: bool,
+
+    /// Generate an IndexNow key (with --id, make it the key of that source)
+    #[arg(long)]
+    keygen: bool,
+
+    /// Length of the key generated with --keygen
+    #[arg(long, value_name = "N", default_value_t = keyfile::DEFAULT_KEY_LENGTH as u16,
+          value_parser = clap::value_parser!(u16).range(keyfile::MIN_KEY_LENGTH as i64..=keyfile::MAX_KEY_LENGTH as i64))]
+    key_length: u16,
+
+    /// Directory to write the KEY.txt file generated with --keygen to
+    #[arg(long, value_name = "DIR", requires = "keygen")]
+    key_dir: Option<PathBuf>,
+
+    /// Submit the given URLs right away (as arguments, with --file, or on stdin)
+    #[arg(long)]
+    submit: bool,
+
+    /// URLs to submit with --submit
+    #[arg(value_name = "URL", requires = "submit")]
+    urls: Vec<String>,
+
+    /// File with one URL per line to submit with --submit ('-' for stdin)
+    #[arg(long, value_name = "FILE", requires = "submit")]
+    file: Option<String>,
+
+    /// Track the URLs given to --submit under the --source
+    #[arg(long, requires = "submit")]
+    record: bool,
+
+    /// Show submission history (filter with -e, --history-url, --since, --until, --status)
+    #[arg(long)]
+    history: bool,
+
+    /// Only show history of requests that included a URL containing this text
+    #[arg(long, value_name = "TEXT", requires = "history")]
+    history_url: Option<String>,
+
+    /// Source URL for --add/--config
+    #[arg(long, value_name = "URL", conflicts_with = "history")]
+    url: Option<String>,
+
+    /// Submit stored URLs of the sources given with -e again, changed or not
+    #[arg(long)]
+    resubmit: bool,
+
+    /// Only resubmit URLs matching this regular expression
+    #[arg(long = "match", value_name = "REGEX", requires = "resubmit")]
+    match_pattern: Option<String>,
+
+    /// Only show history, or resubmit URLs last submitted, from this date on (YYYY-MM-DD, UTC)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    since: Option<String>,
+
+    /// Only show history, or resubmit URLs last submitted, up to and including this date (YYYY-MM-DD, UTC)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    until: Option<String>,
+
+    /// Only show history with this result: ok, failed, or an HTTP status code
+    #[arg(long, value_parser = history::parse_status_filter)]
+    status: Option<db::StatusFilter>,
+
+    /// Maximum number of history entries to show
+    #[arg(long, default_value_t = history::DEFAULT_LIMIT)]
+    limit: usize,
+
+    /// Attempts before a queued submission is dead-lettered
+    #[arg(long, default_value_t = queue::DEFAULT_MAX_ATTEMPTS, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Output format: text, json (one document) or ndjson (one record per line)
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Database file (default: IXFEED_DB, then the platform data directory)
+    #[arg(long, value_name = "PATH")]
+    db: Option<PathBuf>,
+
     /// Show version information
     #[arg(short = 'v', long)]
     version: bool,
@@ -81,77 +300,169 @@ struct Cli {
 
 fn main() {
     let cli = Cli::parse();
+    output::init(cli.output);
+    run(cli);
+    output::finish();
+}
 
+fn run(cli: Cli) {
     if cli.help {
         print_help();
         return;
     }
 
     if cli.version {
-        println!(
+        say!(
             "{} v{} {}",
             env!("CARGO_PKG_NAME"),
             env!("CARGO_PKG_VERSION"),
             "(ALPHA)".yellow().bold()
         );
-        println!();
-        println!(
+        say!();
+        say!(
             "{}  {}: This software is in alpha stage.",
             "⚠️".yellow(),
             "WARNING".yellow().bold()
         );
-        println!("   Features may be incomplete, unstable, or change without notice.");
-        println!();
-        println!("Copyright (C) 2026 Andre Franca");
-        println!("Licensed under the GNU AGPL v3.0 or later.");
-        println!("See <https://www.gnu.org/licenses/agpl-3.0.html> for details.");
+        say!("   Features may be incomplete, unstable, or change without notice.");
+        say!();
+        say!("Copyright (C) 2026 Andre Franca");
+        say!("Licensed under the GNU AGPL v3.0 or later.");
+        say!("See <https://www.gnu.org/licenses/agpl-3.0.html> for details.");
+        return;
+    }
+
+    let db_path = match db::db_path(cli.db.as_deref()) {
+        Ok(path) => path,
+        Err(e) => exit_with_error(e),
+    };
+
+    if let Some(path) = &cli.apply {
+        if let Err(e) = sync::apply_file(&db_path, path, cli.dry_run, cli.unattended) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    if cli.keygen {
+        if let Err(e) = keyfile::keygen(&db_path, usize::from(cli.key_length), cli.key_dir.as_deref(), cli.id) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    if cli.submit {
+        let args = adhoc::AdhocArgs {
+            urls: cli.urls.clone(),
+            file: cli.file.clone(),
+            source: cli.id,
+            key: cli.key.clone(),
+            host: cli.host.clone(),
+            key_location: cli.key_location.clone(),
+            endpoints: cli.engine.as_deref().map(config::parse_endpoints),
+            record: cli.record,
+            dry_run: cli.dry_run,
+        };
+        if let Err(e) = adhoc::run(&db_path, &args) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    // Source settings given as flags make --add, --config and --remove non-interactive
+    let source_args = manage::SourceArgs {
+        source_type: cli.source_type,
+        url: cli.url.clone(),
+        key: cli.key.clone(),
+        host: cli.host.clone(),
+        key_location: if cli.no_key_location {
+            Some(None)
+        } else {
+            cli.key_location.clone().map(Some)
+        },
+        endpoints: cli.engine.as_deref().map(config::parse_endpoints),
+        poll_interval: cli.interval.filter(|_| cli.add || cli.config),
+        filters: filter_args(&cli.include, &cli.exclude, cli.no_filters),
+        extra_hosts: if cli.no_extra_hosts {
+            Some(Vec::new())
+        } else if cli.extra_host.is_empty() {
+            None
+        } else {
+            Some(cli.extra_host.clone())
+        },
+        trailing_slash: cli.trailing_slash,
+        strip_tracking: cli.strip_tracking,
+        sort_query: cli.sort_query,
+        removal_grace: if cli.no_removals {
+            Some(None)
+        } else {
+            cli.removal_grace.map(Some)
+        },
+        removal_probe: cli.probe_removals,
+        validate: !cli.no_validate,
+    };
+    let non_interactive = cli.id.is_some() || !source_args.is_empty();
+
+    if cli.config && non_interactive {
+        exit_on_manage_error(manage::edit_source(&db_path, cli.id, &source_args));
+        return;
+    }
+
+    if cli.add && non_interactive {
+        exit_on_manage_error(manage::add_source(&db_path, &source_args));
+        return;
+    }
+
+    if cli.remove && non_interactive {
+        exit_on_manage_error(manage::remove_source(&db_path, cli.id));
         return;
     }
 
     if cli.config {
-        if let Err(e) = config::edit_config() {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = config::edit_config(&db_path) {
+            exit_with_error(e);
         }
         return;
     }
 
     if cli.show {
-        if let Err(e) = config::list_config() {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = config::list_config(&db_path) {
+            exit_with_error(e);
         }
         return;
     }
 
     if cli.add {
-        if let Err(e) = config::add_source_interactive() {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = config::add_source_interactive(&db_path) {
+            exit_with_error(e);
         }
         return;
     }
 
     if cli.remove {
-        if let Err(e) = config::remove_source_interactive() {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = config::remove_source_interactive(&db_path) {
+            exit_with_error(e);
         }
         return;
     }
 
     if cli.list {
-        if let Err(e) = config::list_sources() {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = config::list_sources(&db_path) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    if cli.queue {
+        if let Err(e) = queue::show_queue(&db_path) {
+            exit_with_error(e);
         }
         return;
     }
 
     if cli.clear_db {
-        if let Err(e) = db::clear_database() {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = db::clear_database(&db_path, cli.yes) {
+            exit_with_error(e);
         }
         return;
     }
@@ -160,29 +471,115 @@ fn main() {
     let resolved_entry: Option<Vec<i64>> = match &cli.entry {
         Some(ids) if ids.is_empty() => {
             // -e flag provided without IDs, prompt user
-            match prompt_for_source_ids() {
+            match prompt_for_source_ids(&db_path) {
                 Ok(ids) => Some(ids),
-                Err(e) => {
-                    eprintln!("{}: {}", "Error".red().bold(), e);
-                    process::exit(1);
-                }
+                Err(e) => exit_with_error(e),
             }
         }
         other => other.clone(),
     };
 
+    if cli.history {
+        let filter = db::HistoryFilter {
+            source_ids: resolved_entry.clone(),
+            url_contains: cli.history_url.clone(),
+            since: cli.since.clone(),
+            until: cli.until.clone(),
+            status: cli.status,
+            limit: cli.limit,
+        };
+        if let Err(e) = history::show_history(&db_path, &filter) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    if cli.vacuum {
+        if let Err(e) = maintenance::vacuum(&db_path, cli.dry_run, cli.yes) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    let maintenance_action = if cli.reset {
+        Some(maintenance::Action::Reset)
+    } else if let Some(days) = cli.prune {
+        Some(maintenance::Action::Prune { days })
+    } else {
+        cli.forget.clone().map(|pattern| maintenance::Action::Forget { pattern })
+    };
+    if let Some(action) = maintenance_action {
+        if cli.reset && resolved_entry.is_none() {
+            exit_with_error("--reset needs the sources to reset, e.g. -e 1");
+        }
+        let result = get_sources_to_process(&db_path, resolved_entry.as_deref())
+            .and_then(|sources| maintenance::run(&db_path, &sources, &action, cli.dry_run, cli.yes));
+        if let Err(e) = result {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    if cli.resubmit {
+        let Some(ids) = resolved_entry.as_deref() else {
+            exit_with_error("--resubmit needs the sources to resubmit, e.g. -e 1,2");
+        };
+        let args = resubmit::ResubmitArgs {
+            pattern: cli.match_pattern.clone(),
+            since: cli.since.clone(),
+            until: cli.until.clone(),
+            dry_run: cli.dry_run,
+            unattended: cli.unattended,
+            max_attempts: cli.max_attempts,
+        };
+        let result = get_sources_to_process(&db_path, Some(ids))
+            .and_then(|sources| resubmit::run(&db_path, &sources, &args));
+        if let Err(e) = result {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    if cli.verify {
+        let failed = get_sources_to_process(&db_path, resolved_entry.as_deref()).and_then(|sources| {
+            let conn = db::init_db(&db_path)?;
+            keyfile::verify(&conn, &sources)
+        });
+        match failed {
+            Ok(0) => {}
+            Ok(failed) => exit_with_error(format!("{} key file(s) failed the check", failed)),
+            Err(e) => exit_with_error(e),
+        }
+        return;
+    }
+
     if cli.dry_run {
-        if let Err(e) = run_dry_run(resolved_entry.as_deref()) {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = run_dry_run(&db_path, resolved_entry.as_deref()) {
+            exit_with_error(e);
+        }
+        return;
+    }
+
+    let options = RunOptions {
+        unattended: cli.unattended || cli.watch,
+        max_attempts: cli.max_attempts,
+    };
+
+    if cli.watch {
+        if cli.output == OutputFormat::Json {
+            exit_with_error("--output json writes its records only when ixfeed exits, so it can't be used with --watch; use --output ndjson");
+        }
+        let default_interval = cli.interval.unwrap_or(watch::DEFAULT_POLL_INTERVAL);
+        let process: Arc<watch::ProcessFn> = Arc::new(move |conn, source| process_source(conn, source, &options));
+        if let Err(e) = watch::run(&db_path, resolved_entry.as_deref(), default_interval, process) {
+            exit_with_error(e);
         }
         return;
     }
 
     if cli.unattended {
-        if let Err(e) = run_unattended_submission(resolved_entry.as_deref()) {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = run_unattended_submission(&db_path, resolved_entry.as_deref(), &options) {
+            exit_with_error(e);
         }
         return;
     }
@@ -190,51 +587,126 @@ fn main() {
     // Default: run submission workflow
     {
         // Check if we have any sources
-        if !config::has_sources() {
-            println!(
+        if !config::has_sources(&db_path) {
+            say!(
                 "{} No sources configured. Let's add one.\n",
                 "ℹ".cyan().bold()
             );
-            if let Err(e) = config::add_source_interactive() {
-                eprintln!("{}: {}", "Error".red().bold(), e);
-                process::exit(1);
+            if let Err(e) = config::add_source_interactive(&db_path) {
+                exit_with_error(e);
             }
-            println!();
+            say!();
         }
         
         // Now run the submission workflow
-        if let Err(e) = run_submission(resolved_entry.as_deref()) {
-            eprintln!("{}: {}", "Error".red().bold(), e);
-            process::exit(1);
+        if let Err(e) = run_submission(&db_path, resolved_entry.as_deref(), &options) {
+            exit_with_error(e);
         }
     }
 }
 
+/// Report an error on stderr (and as an error record) and exit with status 1
+fn exit_with_error(e: impl std::fmt::Display) -> ! {
+    output::emit(&Record::Error { message: e.to_string() });
+    output::finish();
+    eprintln!("{}: {}", "Error".red().bold(), e);
+    process::exit(1);
+}
+
+/// Report a non-interactive command's error on stderr and exit with its status
+fn exit_on_manage_error<T>(result: Result<T, manage::ManageError>) {
+    if let Err(e) = result {
+        output::emit(&Record::Error { message: e.to_string() });
+        output::finish();
+        eprintln!("{}", e);
+        process::exit(e.kind.exit_code());
+    }
+}
+
 fn print_help() {
-    println!(
+    say!(
         "{} - RSS/Atom/JSON feed and sitemap watcher for IndexNow",
         env!("CARGO_PKG_NAME").bold()
     );
-    println!();
-    println!("{}", "Usage:".bold());
-    println!("  {} [OPTIONS]", env!("CARGO_PKG_NAME"));
-    println!();
-    println!("{}", "Options:".bold());
-    println!("  {}, {}     Edit configuration (API key, host, search engine)", "-c".cyan(), "--config".cyan());
-    println!("  {}, {}       Show current configuration and sources", "-s".cyan(), "--show".cyan());
-    println!("  {}, {}        Add a new source (feed or sitemap)", "-a".cyan(), "--add".cyan());
-    println!("  {}, {}     Remove a source", "-r".cyan(), "--remove".cyan());
-    println!("  {}, {}       List all configured sources", "-l".cyan(), "--list".cyan());
-    println!("  {}, {} {} Process only specific sources (comma-separated IDs)", "-e".cyan(), "--entry".cyan(), "<IDs>".dimmed());
-    println!("      {}   Clear the database (WARNING: destructive operation)", "--clear-db".cyan());
-    println!("  {}, {}    Dry run - show URLs that would be submitted", "-d".cyan(), "--dry-run".cyan());
-    println!("  {}, {} Submit URLs without confirmation (for automation)", "-u".cyan(), "--unattended".cyan());
-    println!("  {}, {}    Show version information", "-v".cyan(), "--version".cyan());
-    println!("  {}, {}       Show this help message", "-h".cyan(), "--help".cyan());
-}
-
-fn get_sources_to_process(entry_filter: Option<&[i64]>) -> Result<Vec<db::Source>, Box<dyn std::error::Error>> {
-    let all_sources = config::get_sources()?;
+    say!();
+    say!("{}", "Usage:".bold());
+    say!("  {} [OPTIONS]", env!("CARGO_PKG_NAME"));
+    say!();
+    say!("{}", "Options:".bold());
+    say!("  {}, {}     Edit configuration (API key, host, search engine)", "-c".cyan(), "--config".cyan());
+    say!("  {}, {}       Show current configuration and sources", "-s".cyan(), "--show".cyan());
+    say!("  {}, {}        Add a new source (feed or sitemap)", "-a".cyan(), "--add".cyan());
+    say!("  {}, {}     Remove a source", "-r".cyan(), "--remove".cyan());
+    say!("  {}, {}       List all configured sources", "-l".cyan(), "--list".cyan());
+    say!("  {}, {} {} Process only specific sources (comma-separated IDs)", "-e".cyan(), "--entry".cyan(), "<IDs>".dimmed());
+    say!("      {}      Show queued and dead-lettered submissions", "--queue".cyan());
+    say!("      {}     Check the IndexNow key file of each source (or those given with -e)", "--verify".cyan());
+    say!("      {}     Generate an IndexNow key; {} writes KEY.txt there, {} attaches it to a source", "--keygen".cyan(), "--key-dir <DIR>".dimmed(), "--id <ID>".dimmed());
+    say!("      {} {} Length of the generated key (default: {})", "--key-length".cyan(), "<N>".dimmed(), keyfile::DEFAULT_KEY_LENGTH);
+    say!("      {} {} Submit URLs now; also {} ('-' for stdin) or piped on stdin", "--submit".cyan(), "<URL>...".dimmed(), "--file <FILE>".dimmed());
+    say!("               Uses {} settings, or {} {} {}; {} tracks them under the source", "--source <ID>".dimmed(), "--key".dimmed(), "[--host]".dimmed(), "[--engine]".dimmed(), "--record".dimmed());
+    say!("      {} {} Attempts before a queued submission is dead-lettered (default: {})", "--max-attempts".cyan(), "<N>".dimmed(), queue::DEFAULT_MAX_ATTEMPTS);
+    say!("      {}    Show submission history", "--history".cyan());
+    say!("      {} {} Filter history by URL substring", "--history-url".cyan(), "<TEXT>".dimmed());
+    say!("      {} {} Filter history from this date (YYYY-MM-DD)", "--since".cyan(), "<DATE>".dimmed());
+    say!("      {} {} Filter history up to this date (YYYY-MM-DD)", "--until".cyan(), "<DATE>".dimmed());
+    say!("      {} {} Filter history by result: ok, failed, or HTTP code", "--status".cyan(), "<S>".dimmed());
+    say!("      {}   Submit stored URLs of the sources given with -e again; narrow with {} and {}/{}", "--resubmit".cyan(), "--match <REGEX>".dimmed(), "--since".dimmed(), "--until".dimmed());
+    say!("      {} {}   Maximum history entries to show (default: {})", "--limit".cyan(), "<N>".dimmed(), history::DEFAULT_LIMIT);
+    say!("      {}   Clear the database (WARNING: destructive operation)", "--clear-db".cyan());
+    say!("      {}      Forget the tracked URLs of the sources given with -e and start their first run over", "--reset".cyan());
+    say!("      {} {} Forget URLs not listed for N days (scope with -e)", "--prune".cyan(), "<N>".dimmed());
+    say!("      {} {} Forget URLs matching a glob or re:regex (scope with -e)", "--forget".cyan(), "<P>".dimmed());
+    say!("      {}     Reclaim the space of deleted rows; {} shows the counts, {} skips the prompt", "--vacuum".cyan(), "-d".dimmed(), "--yes".dimmed());
+    say!("  {}, {}    Dry run - show URLs that would be submitted", "-d".cyan(), "--dry-run".cyan());
+    say!("  {}, {} Submit URLs without confirmation (for automation)", "-u".cyan(), "--unattended".cyan());
+    say!("  {}, {}      Stay resident and poll each source on its own interval", "-w".cyan(), "--watch".cyan());
+    say!("      {} {} Poll interval for --add/--config, or the --watch default (default: 1h)", "--interval".cyan(), "<I>".dimmed());
+    say!("      {} {} Output format: text, json or ndjson (records on stdout, progress on stderr)", "--output".cyan(), "<FORMAT>".dimmed());
+    say!("      {} {}  Database file (default: ${} or the platform data directory)", "--db".cyan(), "<PATH>".dimmed(), db::DB_ENV);
+    say!("  {}, {}    Show version information", "-v".cyan(), "--version".cyan());
+    say!("  {}, {}       Show this help message", "-h".cyan(), "--help".cyan());
+    say!();
+    say!("{}", "Non-interactive source management:".bold());
+    say!("  {} {} {} {} {}", "--add".cyan(), "--url <URL>".cyan(), "--key <KEY>".cyan(), "[--type <T>] [--host <H>] [--key-location <URL>] [--engine <E1,E2>] [--interval <I>] [--include <P>]... [--exclude <P>]... [--extra-host <H=K>]...".dimmed(), "[--trailing-slash <P>] [--strip-tracking <B>] [--sort-query <B>] [--removal-grace <I>] [--probe-removals <B>] [--no-validate]".dimmed());
+    say!("  {} {} {} {}", "--config".cyan(), "--id <ID>".cyan(), "[--type <T>] [--url <URL>] [--key <K>] [--host <H>] [--key-location <URL>] [--no-key-location] [--engine <E>] [--interval <I>] [--include <P>]... [--exclude <P>]... [--no-filters] [--extra-host <H=K>]... [--no-extra-hosts]".dimmed(), "[--trailing-slash <P>] [--strip-tracking <B>] [--sort-query <B>] [--removal-grace <I>] [--no-removals] [--probe-removals <B>] [--no-validate]".dimmed());
+    say!("  {} {}", "--remove".cyan(), "--id <ID>".cyan());
+    say!("  {} {}  Sync sources with a TOML file (plan only with -d, no prompt with -u)", "--apply".cyan(), "<FILE>".cyan());
+    say!("  Key location: --key-location https://example.com/.well-known/KEY.txt when the key file is not at the host root.");
+    say!("  Extra hosts: --extra-host blog.example.com=KEY[=KEY_LOCATION] lets a source submit URLs of another host with its own key.");
+    say!("  Removals: --removal-grace 7d submits URLs missing from the source for 7 days as removed, once they answer 404 or 410 (unless --probe-removals false).");
+    say!("  URL normalization: trailing slash keep (default), add or remove; tracking params stripped unless --strip-tracking false; query sorted only with --sort-query true.");
+    say!("  Filter patterns: globs ('/tag/*' matches path and query, others the full URL) or 're:<regex>'.");
+    say!("  Types: feed, sitemap, robots. Errors are printed as 'error[<code>]: <message>' with a non-zero exit status.");
+}
+
+/// Filter rules given with --include/--exclude, or an empty set for --no-filters
+fn filter_args(include: &[String], exclude: &[String], clear: bool) -> Option<Vec<filter::Rule>> {
+    if include.is_empty() && exclude.is_empty() && !clear {
+        return None;
+    }
+    let rule = |kind| move |pattern: &String| filter::Rule { kind, pattern: pattern.clone() };
+    Some(
+        include
+            .iter()
+            .map(rule(filter::RuleKind::Include))
+            .chain(exclude.iter().map(rule(filter::RuleKind::Exclude)))
+            .collect(),
+    )
+}
+
+/// Validate a `YYYY-MM-DD` date argument
+fn parse_date(value: &str) -> Result<String, String> {
+    let re = regex::Regex::new(r"^\d{4}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01])$").expect("valid regex");
+    if re.is_match(value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("expected a date in YYYY-MM-DD format, got '{}'", value))
+    }
+}
+
+fn get_sources_to_process(db_path: &Path, entry_filter: Option<&[i64]>) -> Result<Vec<db::Source>, Box<dyn std::error::Error>> {
+    let all_sources = config::get_sources(db_path)?;
     
     if all_sources.is_empty() {
         return Err("No sources configured. Run 'ixfeed --add' to add a source.".into());
@@ -261,25 +733,22 @@ fn get_sources_to_process(entry_filter: Option<&[i64]>) -> Result<Vec<db::Source
 }
 
 /// Prompt user to select source IDs when -e/--entry is provided without IDs
-fn prompt_for_source_ids() -> Result<Vec<i64>, Box<dyn std::error::Error>> {
-    let sources = config::get_sources()?;
+fn prompt_for_source_ids(db_path: &Path) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+    let sources = config::get_sources(db_path)?;
     
     if sources.is_empty() {
         return Err("No sources configured. Run 'ixfeed --add' to add a source.".into());
     }
     
-    println!("{}", "Available sources:".bold());
+    say!("{}", "Available sources:".bold());
     for source in &sources {
-        let type_str = match source.source_type.as_str() {
-            "sitemap" => "Sitemap".cyan(),
-            _ => "Feed".cyan(),
-        };
+        let type_str = SourceType::from_db(&source.source_type).label().cyan();
         let status = if source.first_run_completed {
             "synced".green()
         } else {
             "new".yellow()
         };
-        println!(
+        say!(
             "  ID {} [{}] {} ({})",
             source.id.to_string().bold(),
             type_str,
@@ -288,7 +757,7 @@ fn prompt_for_source_ids() -> Result<Vec<i64>, Box<dyn std::error::Error>> {
         );
     }
     
-    println!();
+    say!();
     let input: String = Input::new()
         .with_prompt("Enter source ID(s) to process (comma-separated, e.g., 1,2,3)")
         .interact_text()?;
@@ -312,12 +781,12 @@ fn prompt_for_source_ids() -> Result<Vec<i64>, Box<dyn std::error::Error>> {
     Ok(ids)
 }
 
-fn run_dry_run(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error::Error>> {
+fn run_dry_run(db_path: &Path, entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize database
-    let conn = db::init_db()?;
+    let conn = db::init_db(db_path)?;
     
     // Get sources to process
-    let sources = get_sources_to_process(entry_filter)?;
+    let sources = get_sources_to_process(db_path, entry_filter)?;
     
     // Validate that all sources have required config
     for source in &sources {
@@ -329,16 +798,16 @@ fn run_dry_run(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error::E
         }
     }
 
-    println!(
+    say!(
         "{} {} Dry Run Mode {}",
         "═".repeat(15).blue(),
         "DRY RUN".yellow().bold(),
         "═".repeat(15).blue()
     );
-    println!("{}", "No URLs will be submitted.\n".dimmed());
+    say!("{}", "No URLs will be submitted.\n".dimmed());
     
     if sources.len() > 1 {
-        println!(
+        say!(
             "{} Processing {} sources...\n",
             "ℹ".cyan().bold(),
             sources.len()
@@ -348,11 +817,11 @@ fn run_dry_run(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error::E
     for source in &sources {
         dry_run_source(&conn, source)?;
         if sources.len() > 1 {
-            println!();
+            say!();
         }
     }
 
-    println!(
+    say!(
         "\n{} To actually submit, run: {}",
         "→".blue().bold(),
         env!("CARGO_PKG_NAME").cyan()
@@ -362,14 +831,9 @@ fn run_dry_run(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error::E
 }
 
 fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<(), Box<dyn std::error::Error>> {
-    let source_type = if source.source_type == "sitemap" {
-        SourceType::Sitemap
-    } else {
-        SourceType::Feed
-    };
-    let source_type_str = if source.source_type == "sitemap" { "sitemap" } else { "feed" };
+    let source_type_str = SourceType::from_db(&source.source_type).noun();
     
-    println!(
+    say!(
         "{} [ID {}] Fetching {} from {}...",
         "→".blue().bold(),
         source.id.to_string().bold(),
@@ -377,13 +841,25 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
         source.source_url
     );
 
-    let entries: Vec<UrlEntry> = match source_type {
-        SourceType::Feed => feed::fetch_feed_urls(&source.source_url)?,
-        SourceType::Sitemap => sitemap::fetch_sitemap_urls(&source.source_url)?,
-    };
+    keyfile::preflight(conn, source, true)?;
+
+    for endpoint in &source.endpoints {
+        let due = db::get_due_submissions(conn, source.id, endpoint, queue::unix_now())?;
+        if !due.is_empty() {
+            say!(
+                "  {} {} queued URL(s) would be retried on {}.",
+                "↻".cyan().bold(),
+                due.len(),
+                endpoint
+            );
+        }
+    }
+
+    let entries = fetch_filtered_entries(conn, source, true)?;
 
     if entries.is_empty() {
-        println!(
+        emit_summary(source, true, false, 0, &[], 0);
+        say!(
             "  {} No URLs found in {}.",
             "⚠".yellow().bold(),
             source_type_str
@@ -395,7 +871,11 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
     let is_first_run = db::is_source_first_run(conn, source.id)?;
 
     if is_first_run {
-        println!(
+        let submit_entries = first_run_entries(&entries);
+        emit_summary(source, true, true, entries.len(), &submit_entries, 0);
+        emit_urls(source, &submit_entries, &[], true);
+
+        say!(
             "  {} First run detected. {} URL(s) found:\n",
             "ℹ".cyan().bold(),
             entries.len()
@@ -403,7 +883,7 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
         
         for (i, entry) in entries.iter().take(10).enumerate() {
             let date_str = entry.date.as_deref().unwrap_or("no date");
-            println!(
+            say!(
                 "    {}. {} {}",
                 (i + 1).to_string().dimmed(),
                 entry.url.green(),
@@ -411,37 +891,34 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
             );
         }
         if entries.len() > 10 {
-            println!("    {} ... and {} more", "".dimmed(), entries.len() - 10);
+            say!("    {} ... and {} more", "".dimmed(), entries.len() - 10);
         }
 
-        println!(
+        say!(
             "\n  {} On actual run, you would be asked to confirm submission of all {} URL(s).",
             "ℹ".cyan().bold(),
             entries.len()
         );
     } else {
-        // Check for new or modified URLs
+        // Check for new, modified, or previously failed URLs
         let stored_urls = db::get_urls_with_dates_for_source(conn, source.id)?;
-        
-        let mut new_urls: Vec<&UrlEntry> = Vec::new();
-        let mut modified_urls: Vec<(&UrlEntry, Option<String>)> = Vec::new();
-
-        for entry in &entries {
-            if let Some(stored_date) = stored_urls.get(&entry.url) {
-                // URL exists - check if modified
-                if entry.date.is_some() && entry.date != *stored_date {
-                    modified_urls.push((entry, stored_date.clone()));
-                }
-            } else {
-                // New URL
-                new_urls.push(entry);
-            }
-        }
+        let mut changes = collect_changes(&entries, &stored_urls);
+        changes.extend(removals::find(conn, source, &entries, true)?);
+        let plans = plan_submissions(conn, source, &changes)?;
+        let retry_urls = retry_targets(&plans);
+        let dates: HashMap<&str, Option<&str>> = entries
+            .iter()
+            .map(|e| (e.url.as_str(), e.date.as_deref()))
+            .collect();
 
-        let total_to_submit = new_urls.len() + modified_urls.len();
+        let new_urls: Vec<&SubmitEntry> = changes.iter().filter(|e| matches!(e.reason, SubmitReason::New)).collect();
+        let modified_urls: Vec<&SubmitEntry> = changes.iter().filter(|e| matches!(e.reason, SubmitReason::Modified { .. })).collect();
+        let removed_urls: Vec<&SubmitEntry> = changes.iter().filter(|e| matches!(e.reason, SubmitReason::Removed)).collect();
+        emit_summary(source, true, false, entries.len(), &changes, retry_urls.len());
+        emit_urls(source, &changes, &retry_urls, true);
 
-        if total_to_submit == 0 {
-            println!(
+        if changes.is_empty() && retry_urls.is_empty() {
+            say!(
                 "  {} No new or modified URLs to submit. All {} URL(s) are up to date.",
                 "✓".green().bold(),
                 entries.len()
@@ -449,17 +926,18 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
             return Ok(());
         }
 
-        println!(
-            "  {} Would submit {} URL(s):\n",
+        say!(
+            "  {} Would submit {} URL(s) to {}:\n",
             "ℹ".cyan().bold(),
-            total_to_submit
+            changes.len() + retry_urls.len(),
+            source.endpoints.join(", ")
         );
 
         if !new_urls.is_empty() {
-            println!("  {} ({}):", "New URLs".green().bold(), new_urls.len());
+            say!("  {} ({}):", "New URLs".green().bold(), new_urls.len());
             for (i, entry) in new_urls.iter().take(5).enumerate() {
-                let date_str = entry.date.as_deref().unwrap_or("no date");
-                println!(
+                let date_str = dates.get(entry.url.as_str()).copied().flatten().unwrap_or("no date");
+                say!(
                     "    {}. {} {}",
                     (i + 1).to_string().dimmed(),
                     entry.url,
@@ -467,19 +945,25 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
                 );
             }
             if new_urls.len() > 5 {
-                println!("    {} ... and {} more", "".dimmed(), new_urls.len() - 5);
+                say!("    {} ... and {} more", "".dimmed(), new_urls.len() - 5);
             }
         }
 
         if !modified_urls.is_empty() {
             if !new_urls.is_empty() {
-                println!();
+                say!();
             }
-            println!("  {} ({}):", "Modified URLs".yellow().bold(), modified_urls.len());
-            for (i, (entry, old_date)) in modified_urls.iter().take(5).enumerate() {
-                let old_str = old_date.as_deref().unwrap_or("unknown");
-                let new_str = entry.date.as_deref().unwrap_or("unknown");
-                println!(
+            say!("  {} ({}):", "Modified URLs".yellow().bold(), modified_urls.len());
+            for (i, entry) in modified_urls.iter().take(5).enumerate() {
+                let old_str = stored_urls
+                    .get(&entry.url)
+                    .and_then(|s| s.last_modified.as_deref())
+                    .unwrap_or("unknown");
+                let new_str = match &entry.reason {
+                    SubmitReason::Modified { date } => date.as_str(),
+                    _ => "unknown",
+                };
+                say!(
                     "    {}. {} {} → {}",
                     (i + 1).to_string().dimmed(),
                     entry.url,
@@ -488,7 +972,42 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
                 );
             }
             if modified_urls.len() > 5 {
-                println!("    {} ... and {} more", "".dimmed(), modified_urls.len() - 5);
+                say!("    {} ... and {} more", "".dimmed(), modified_urls.len() - 5);
+            }
+        }
+
+        if !retry_urls.is_empty() {
+            if !new_urls.is_empty() || !modified_urls.is_empty() {
+                say!();
+            }
+            say!("  {} ({}):", "Retried URLs".cyan().bold(), retry_urls.len());
+            for (i, (url, endpoints)) in retry_urls.iter().take(5).enumerate() {
+                say!(
+                    "    {}. {} {}",
+                    (i + 1).to_string().dimmed(),
+                    url,
+                    format!("(not yet accepted by {})", endpoints.join(", ")).dimmed()
+                );
+            }
+            if retry_urls.len() > 5 {
+                say!("    {} ... and {} more", "".dimmed(), retry_urls.len() - 5);
+            }
+        }
+
+        if !removed_urls.is_empty() {
+            if !new_urls.is_empty() || !modified_urls.is_empty() || !retry_urls.is_empty() {
+                say!();
+            }
+            say!("  {} ({}):", "Removed URLs".red().bold(), removed_urls.len());
+            for (i, entry) in removed_urls.iter().take(5).enumerate() {
+                say!(
+                    "    {}. {}",
+                    (i + 1).to_string().dimmed(),
+                    entry.url
+                );
+            }
+            if removed_urls.len() > 5 {
+                say!("    {} ... and {} more", "".dimmed(), removed_urls.len() - 5);
             }
         }
     }
@@ -496,12 +1015,18 @@ fn dry_run_source(conn: &rusqlite::Connection, source: &db::Source) -> Result<()
     Ok(())
 }
 
-fn run_submission(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error::Error>> {
+/// Options shared by every source processed in a run
+struct RunOptions {
+    unattended: bool,
+    max_attempts: u32,
+}
+
+fn run_submission(db_path: &Path, entry_filter: Option<&[i64]>, options: &RunOptions) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize database
-    let conn = db::init_db()?;
+    let conn = db::init_db(db_path)?;
     
     // Get sources to process
-    let sources = get_sources_to_process(entry_filter)?;
+    let sources = get_sources_to_process(db_path, entry_filter)?;
     
     // Validate that all sources have required config
     for source in &sources {
@@ -514,7 +1039,7 @@ fn run_submission(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error
     }
     
     if sources.len() > 1 {
-        println!(
+        say!(
             "{} Processing {} sources...\n",
             "ℹ".cyan().bold(),
             sources.len()
@@ -522,21 +1047,21 @@ fn run_submission(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error
     }
     
     for (idx, source) in sources.iter().enumerate() {
-        process_source(&conn, source, false)?;
+        process_source(&conn, source, options)?;
         if idx < sources.len() - 1 {
-            println!();
+            say!();
         }
     }
 
     Ok(())
 }
 
-fn run_unattended_submission(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn std::error::Error>> {
+fn run_unattended_submission(db_path: &Path, entry_filter: Option<&[i64]>, options: &RunOptions) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize database
-    let conn = db::init_db()?;
+    let conn = db::init_db(db_path)?;
     
     // Get sources to process
-    let sources = get_sources_to_process(entry_filter)?;
+    let sources = get_sources_to_process(db_path, entry_filter)?;
     
     // Validate that all sources have required config
     for source in &sources {
@@ -549,7 +1074,7 @@ fn run_unattended_submission(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn
     }
     
     if sources.len() > 1 {
-        println!(
+        say!(
             "{} Processing {} sources (unattended)...\n",
             "ℹ".cyan().bold(),
             sources.len()
@@ -557,9 +1082,9 @@ fn run_unattended_submission(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn
     }
     
     for (idx, source) in sources.iter().enumerate() {
-        process_source(&conn, source, true)?;
+        process_source(&conn, source, options)?;
         if idx < sources.len() - 1 {
-            println!();
+            say!();
         }
     }
 
@@ -569,16 +1094,19 @@ fn run_unattended_submission(entry_filter: Option<&[i64]>) -> Result<(), Box<dyn
 fn process_source(
     conn: &rusqlite::Connection,
     source: &db::Source,
-    unattended: bool,
+    options: &RunOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let source_type = if source.source_type == "sitemap" {
-        SourceType::Sitemap
-    } else {
-        SourceType::Feed
-    };
-    let source_type_str = if source.source_type == "sitemap" { "sitemap" } else { "feed" };
+    // A missing or wrong key file makes every request fail, so don't send any
+    if !keyfile::preflight(conn, source, false)? {
+        return Err(format!("Key file check failed for source {}", source.id).into());
+    }
+
+    // Retry anything left in the queue from earlier runs first
+    queue::drain(conn, source, options.max_attempts)?;
+
+    let source_type_str = SourceType::from_db(&source.source_type).noun();
     
-    println!(
+    say!(
         "{} [ID {}] Fetching {} from {}...",
         "→".blue().bold(),
         source.id.to_string().bold(),
@@ -586,18 +1114,16 @@ fn process_source(
         source.source_url
     );
 
-    let entries: Vec<UrlEntry> = match source_type {
-        SourceType::Feed => feed::fetch_feed_urls(&source.source_url)?,
-        SourceType::Sitemap => sitemap::fetch_sitemap_urls(&source.source_url)?,
-    };
+    let entries = fetch_filtered_entries(conn, source, false)?;
 
     if entries.is_empty() {
-        println!(
+        emit_summary(source, false, false, 0, &[], 0);
+        say!(
             "  {} No URLs found in {}.",
             "⚠".yellow().bold(),
             source_type_str
         );
-        println!(
+        say!(
             "  {} Add content to your {} and run again.",
             "→".blue().bold(),
             source_type_str
@@ -605,7 +1131,7 @@ fn process_source(
         return Ok(());
     }
 
-    println!(
+    say!(
         "  {} Found {} URLs in {}.",
         "✓".green().bold(),
         entries.len(),
@@ -616,308 +1142,371 @@ fn process_source(
     let is_first_run = db::is_source_first_run(conn, source.id)?;
 
     if is_first_run {
-        if unattended {
-            handle_first_run_unattended(conn, source, &entries)
-        } else {
-            handle_first_run(conn, source, &entries)
-        }
+        handle_first_run(conn, source, &entries, options)
     } else {
-        if unattended {
-            handle_subsequent_run_unattended(conn, source, &entries)
-        } else {
-            handle_subsequent_run(conn, source, &entries)
-        }
+        handle_subsequent_run(conn, source, &entries, options)
     }
 }
 
-fn handle_first_run(
+/// Fetch a source, normalize its URLs and drop the ones its filter rules
+/// reject or that are on none of its hosts. A dry run reports how many URLs each
+/// rule dropped instead of just the total, and doesn't quarantine anything.
+fn fetch_filtered_entries(
     conn: &rusqlite::Connection,
     source: &db::Source,
-    entries: &[UrlEntry],
-) -> Result<(), Box<dyn std::error::Error>> {
-    println!(
-        "\n  {} First run detected for this source. Found {} URLs.",
-        "ℹ".cyan().bold(),
-        entries.len()
-    );
-
-    // Store all URLs in database first
-    println!("  {} Storing URLs in database...", "→".blue().bold());
-    for entry in entries {
-        db::add_url_with_date_for_source(conn, source.id, &entry.url, entry.date.as_deref())?;
-    }
-    println!(
-        "  {} Stored {} URLs.",
-        "✓".green().bold(),
-        entries.len()
-    );
-
-    // Ask user if they want to submit all URLs
-    println!();
-    println!(
-        "  {} {}",
-        "⚠ WARNING:".yellow().bold(),
-        "Submitting all URLs on first run may include outdated or deprecated links."
-    );
-
-    let should_submit = Confirm::new()
-        .with_prompt(format!("  Do you want to submit all {} found URLs?", entries.len()))
-        .default(false)
-        .interact()?;
-
-    if should_submit {
-        // Build submit entries
-        let submit_entries: Vec<SubmitEntry> = entries
-            .iter()
-            .map(|e| SubmitEntry {
-                url: e.url.clone(),
-                reason: SubmitReason::New,
-            })
-            .collect();
+    dry_run: bool,
+) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
+    let filter = filter::Filter::new(&source.filters)
+        .map_err(|e| format!("Invalid filter rule for source {}: {}", source.id, e))?;
+    let entries = source.normalization.apply_all(fetch_entries(conn, source)?);
+    let entries = if filter.is_empty() {
+        entries
+    } else {
+        apply_filter(source, &filter, entries, dry_run)
+    };
 
-        println!(
-            "\n  {} Submitting {} URL(s) to {}...\n",
-            "→".blue().bold(),
-            submit_entries.len(),
-            source.searchengine
-        );
+    let source_hosts = source.hosts();
+    let (entries, off_host): (Vec<UrlEntry>, Vec<UrlEntry>) = entries
+        .into_iter()
+        .partition(|e| hosts::is_allowed(&e.url, &source_hosts));
+    quarantine_off_host(conn, source, &off_host, dry_run)?;
 
-        submit::submit_in_batches(&source.api_key, &source.host, &source.searchengine, &submit_entries)?;
+    Ok(entries)
+}
 
-        println!(
-            "\n  {} Successfully submitted {} URL(s).",
-            "✓".green().bold(),
-            submit_entries.len()
-        );
-    } else {
-        println!(
-            "\n  {} URLs stored but not submitted.",
-            "ℹ".cyan().bold()
+/// Drop the entries rejected by a source's filter rules and report how many were dropped
+fn apply_filter(source: &db::Source, filter: &filter::Filter, entries: Vec<UrlEntry>, detailed: bool) -> Vec<UrlEntry> {
+    let (entries, report) = filter.apply(entries);
+    output::emit(&Record::Filtered {
+        source_id: source.id,
+        dropped: report.total(),
+        not_included: report.not_included,
+        excluded: report.excluded.iter().map(|(pattern, n)| (pattern.as_str(), *n)).collect(),
+    });
+
+    if detailed {
+        say!(
+            "  {} Filter rules dropped {} URL(s):",
+            "ℹ".cyan().bold(),
+            report.total()
         );
-        println!(
-            "  {} Add new content and run again to submit only the new URLs.",
-            "→".blue().bold()
+        if source.filters.iter().any(|r| r.kind == filter::RuleKind::Include) {
+            say!("    • {} matched no include rule", report.not_included);
+        }
+        for (pattern, dropped) in &report.excluded {
+            say!("    • {} by exclude {}", dropped, pattern.dimmed());
+        }
+    } else if report.total() > 0 {
+        say!(
+            "  {} Filter rules dropped {} URL(s).",
+            "ℹ".cyan().bold(),
+            report.total()
         );
     }
 
-    // Mark first run as completed for this source
-    db::mark_source_first_run_completed(conn, source.id)?;
+    entries
+}
 
+/// Report URLs that are on none of the source's hosts and, unless this is a dry run,
+/// quarantine them. URLs quarantined on an earlier run are only counted.
+fn quarantine_off_host(
+    conn: &rusqlite::Connection,
+    source: &db::Source,
+    off_host: &[UrlEntry],
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if off_host.is_empty() {
+        return Ok(());
+    }
+    let known: HashSet<String> = db::get_urls_with_status(conn, source.id, UrlStatus::OffHost)?
+        .into_iter()
+        .collect();
+    let (earlier, found): (Vec<&str>, Vec<&str>) = off_host
+        .iter()
+        .map(|e| e.url.as_str())
+        .partition(|url| known.contains(*url) && !dry_run);
+
+    if !found.is_empty() {
+        let source_hosts = source.hosts();
+        output::emit(&Record::OffHost {
+            source_id: source.id,
+            hosts: source_hosts.iter().map(|h| h.host.as_str()).collect(),
+            urls: found.clone(),
+            dry_run,
+        });
+        hosts::report_off_host(&source_hosts, &found, if dry_run { 5 } else { 3 }, dry_run);
+        if !dry_run {
+            db::quarantine_urls(conn, source.id, &found)?;
+        }
+    }
+    if !earlier.is_empty() {
+        say!(
+            "  {} Skipped {} off-host URL(s) quarantined on earlier runs.",
+            "ℹ".cyan().bold(),
+            earlier.len()
+        );
+    }
     Ok(())
 }
 
-fn handle_first_run_unattended(
+/// Fetch the URLs currently listed by a source
+fn fetch_entries(conn: &rusqlite::Connection, source: &db::Source) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
+    match SourceType::from_db(&source.source_type) {
+        SourceType::Feed => feed::fetch_feed_urls(conn, &source.source_url),
+        SourceType::Sitemap => sitemap::fetch_sitemap_urls(conn, &source.source_url),
+        SourceType::Robots => robots::fetch_robots_urls(conn, &source.source_url),
+    }
+}
+
+fn handle_first_run(
     conn: &rusqlite::Connection,
     source: &db::Source,
     entries: &[UrlEntry],
+    options: &RunOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!(
+    let submit_entries = first_run_entries(entries);
+    emit_summary(source, false, true, entries.len(), &submit_entries, 0);
+
+    say!(
         "\n  {} First run detected for this source. Found {} URLs.",
         "ℹ".cyan().bold(),
         entries.len()
     );
 
-    // Store all URLs in database first
-    println!("  {} Storing URLs in database...", "→".blue().bold());
-    for entry in entries {
-        db::add_url_with_date_for_source(conn, source.id, &entry.url, entry.date.as_deref())?;
-    }
-    println!(
-        "  {} Stored {} URLs.",
-        "✓".green().bold(),
-        entries.len()
-    );
-
-    // Automatically submit all URLs (unattended mode)
-    println!();
-    println!(
-        "  {} Unattended mode: Submitting all URLs on first run.",
-        "ℹ".cyan().bold()
-    );
-
-    // Build submit entries
-    let submit_entries: Vec<SubmitEntry> = entries
-        .iter()
-        .map(|e| SubmitEntry {
-            url: e.url.clone(),
-            reason: SubmitReason::New,
-        })
-        .collect();
+    say!();
+    let should_submit = if options.unattended {
+        say!(
+            "  {} Unattended mode: Submitting all URLs on first run.",
+            "ℹ".cyan().bold()
+        );
+        true
+    } else {
+        // Ask user if they want to submit all URLs
+        say!(
+            "  {} Submitting all URLs on first run may include outdated or deprecated links.",
+            "⚠ WARNING:".yellow().bold()
+        );
+        Confirm::new()
+            .with_prompt(format!("  Do you want to submit all {} found URLs?", entries.len()))
+            .default(false)
+            .interact()?
+    };
 
-    println!(
-        "\n  {} Submitting {} URL(s) to {}...\n",
-        "→".blue().bold(),
-        submit_entries.len(),
-        source.searchengine
-    );
+    if !should_submit {
+        // Store all URLs as a baseline so only later changes get submitted
+        say!("\n  {} Storing URLs in database...", "→".blue().bold());
+        for entry in entries {
+            db::record_url_for_source(conn, source.id, &entry.url, entry.date.as_deref(), UrlStatus::Baseline)?;
+        }
+        say!(
+            "  {} Stored {} URLs.",
+            "✓".green().bold(),
+            entries.len()
+        );
+        db::mark_source_first_run_completed(conn, source.id)?;
 
-    submit::submit_in_batches(&source.api_key, &source.host, &source.searchengine, &submit_entries)?;
+        say!(
+            "\n  {} URLs stored but not submitted.",
+            "ℹ".cyan().bold()
+        );
+        say!(
+            "  {} Add new content and run again to submit only the new URLs.",
+            "→".blue().bold()
+        );
+        return Ok(());
+    }
 
-    println!(
-        "\n  {} Successfully submitted {} URL(s).",
-        "✓".green().bold(),
-        submit_entries.len()
-    );
+    emit_urls(source, &submit_entries, &[], false);
 
-    // Mark first run as completed for this source
+    // Record URLs as pending before submitting, then mark first run as completed
+    // so that anything not accepted is retried on the next run instead of
+    // triggering another first run
+    record_pending(conn, source, entries, &submit_entries)?;
     db::mark_source_first_run_completed(conn, source.id)?;
 
-    Ok(())
+    let plans = plan_submissions(conn, source, &submit_entries)?;
+    submit_to_endpoints(conn, source, &plans, options)
 }
 
 fn handle_subsequent_run(
     conn: &rusqlite::Connection,
     source: &db::Source,
     entries: &[UrlEntry],
+    options: &RunOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Get stored URLs with their dates for this source
     let stored_urls = db::get_urls_with_dates_for_source(conn, source.id)?;
-
-    let mut to_submit: Vec<SubmitEntry> = Vec::new();
-    let mut new_count = 0;
-    let mut modified_count = 0;
-
-    for entry in entries {
-        if let Some(stored_date) = stored_urls.get(&entry.url) {
-            // URL exists in database - check if it was modified
-            if let Some(new_date) = &entry.date {
-                let is_modified = match stored_date {
-                    Some(old_date) => new_date != old_date,
-                    None => true, // No previous date, treat as modified
-                };
-
-                if is_modified {
-                    to_submit.push(SubmitEntry {
-                        url: entry.url.clone(),
-                        reason: SubmitReason::Modified {
-                            date: new_date.clone(),
-                        },
-                    });
-                    modified_count += 1;
-                }
-            }
-        } else {
-            // New URL - not in database
-            to_submit.push(SubmitEntry {
-                url: entry.url.clone(),
-                reason: SubmitReason::New,
-            });
-            new_count += 1;
-        }
-    }
-
-    if to_submit.is_empty() {
-        println!(
+    let mut changes = collect_changes(entries, &stored_urls);
+    changes.extend(removals::find(conn, source, entries, false)?);
+    let plans = plan_submissions(conn, source, &changes)?;
+    let retry_urls = retry_targets(&plans);
+    emit_summary(source, false, false, entries.len(), &changes, retry_urls.len());
+
+    if changes.is_empty() && retry_urls.is_empty() {
+        say!(
             "  {} No new or modified URLs to submit. All URLs are up to date.",
             "✓".green().bold()
         );
         return Ok(());
     }
 
-    println!(
-        "\n  {} Found {} URL(s) to submit: {} new, {} modified",
+    let new_count = changes.iter().filter(|e| matches!(e.reason, SubmitReason::New)).count();
+    let modified_count = changes.iter().filter(|e| matches!(e.reason, SubmitReason::Modified { .. })).count();
+    let removed_count = changes.iter().filter(|e| matches!(e.reason, SubmitReason::Removed)).count();
+    let retry_count = retry_urls.len();
+    let total = changes.len() + retry_count;
+
+    say!(
+        "\n  {} Found {} URL(s) to submit: {} new, {} modified, {} retried, {} removed",
         "ℹ".cyan().bold(),
-        to_submit.len(),
+        total,
         new_count,
-        modified_count
+        modified_count,
+        retry_count,
+        removed_count
     );
 
     // List URLs to be submitted
     if new_count > 0 {
-        println!("\n  {} ({}):", "New URLs".green().bold(), new_count);
-        for entry in to_submit.iter().filter(|e| matches!(e.reason, SubmitReason::New)).take(5) {
-            println!("    • {}", entry.url);
+        say!("\n  {} ({}):", "New URLs".green().bold(), new_count);
+        for entry in changes.iter().filter(|e| matches!(e.reason, SubmitReason::New)).take(5) {
+            say!("    • {}", entry.url);
         }
         if new_count > 5 {
-            println!("    {} ... and {} more", "".dimmed(), new_count - 5);
+            say!("    {} ... and {} more", "".dimmed(), new_count - 5);
         }
     }
     if modified_count > 0 {
-        println!("\n  {} ({}):", "Modified URLs".yellow().bold(), modified_count);
-        for entry in to_submit.iter().filter(|e| matches!(e.reason, SubmitReason::Modified { .. })).take(5) {
+        say!("\n  {} ({}):", "Modified URLs".yellow().bold(), modified_count);
+        for entry in changes.iter().filter(|e| matches!(e.reason, SubmitReason::Modified { .. })).take(5) {
             if let SubmitReason::Modified { date } = &entry.reason {
-                println!("    • {} (updated: {})", entry.url, date.cyan());
+                say!("    • {} (updated: {})", entry.url, date.cyan());
             }
         }
         if modified_count > 5 {
-            println!("    {} ... and {} more", "".dimmed(), modified_count - 5);
+            say!("    {} ... and {} more", "".dimmed(), modified_count - 5);
+        }
+    }
+    if retry_count > 0 {
+        say!("\n  {} ({}):", "Retried URLs".cyan().bold(), retry_count);
+        for (url, endpoints) in retry_urls.iter().take(5) {
+            say!("    • {} {}", url, format!("({})", endpoints.join(", ")).dimmed());
+        }
+        if retry_count > 5 {
+            say!("    {} ... and {} more", "".dimmed(), retry_count - 5);
+        }
+    }
+    if removed_count > 0 {
+        say!("\n  {} ({}):", "Removed URLs".red().bold(), removed_count);
+        for entry in changes.iter().filter(|e| matches!(e.reason, SubmitReason::Removed)).take(5) {
+            say!("    • {}", entry.url);
+        }
+        if removed_count > 5 {
+            say!("    {} ... and {} more", "".dimmed(), removed_count - 5);
         }
     }
 
-    // Confirm before submitting
-    println!();
-    let should_submit = Confirm::new()
-        .with_prompt(format!("  Submit {} URL(s) to IndexNow?", to_submit.len()))
-        .default(true)
-        .interact()?;
-
-    if !should_submit {
-        println!(
-            "\n  {} Submission cancelled.",
-            "ℹ".cyan().bold()
+    say!();
+    if options.unattended {
+        // Unattended mode: submit without confirmation
+        say!(
+            "  {} Unattended mode: Submitting {} URL(s) without confirmation.",
+            "→".blue().bold(),
+            total
         );
-        return Ok(());
+    } else {
+        // Confirm before submitting
+        let should_submit = Confirm::new()
+            .with_prompt(format!("  Submit {} URL(s) to IndexNow?", total))
+            .default(true)
+            .interact()?;
+
+        if !should_submit {
+            say!(
+                "\n  {} Submission cancelled.",
+                "ℹ".cyan().bold()
+            );
+            return Ok(());
+        }
     }
 
-    println!(
-        "\n  {} Submitting to {}...\n",
-        "→".blue().bold(),
-        source.searchengine
-    );
-
-    submit::submit_in_batches(&source.api_key, &source.host, &source.searchengine, &to_submit)?;
-
-    // Update database with submitted URLs
-    for entry in &to_submit {
-        let date = match &entry.reason {
-            SubmitReason::New => entries
-                .iter()
-                .find(|e| e.url == entry.url)
-                .and_then(|e| e.date.as_deref()),
-            SubmitReason::Modified { date } => Some(date.as_str()),
-        };
-        db::add_url_with_date_for_source(conn, source.id, &entry.url, date)?;
-    }
+    emit_urls(source, &changes, &retry_urls, false);
+    record_pending(conn, source, entries, &changes)?;
+    submit_to_endpoints(conn, source, &plans, options)
+}
 
-    println!(
-        "\n  {} Successfully submitted and stored {} URL(s).",
-        "✓".green().bold(),
-        to_submit.len()
-    );
+/// On a first run every listed URL is submitted as new
+fn first_run_entries(entries: &[UrlEntry]) -> Vec<SubmitEntry> {
+    entries
+        .iter()
+        .map(|e| SubmitEntry {
+            url: e.url.clone(),
+            reason: SubmitReason::New,
+        })
+        .collect()
+}
 
-    Ok(())
+/// Emit the discovered/new/modified/retried/removed counts for a source
+fn emit_summary(source: &db::Source, dry_run: bool, first_run: bool, discovered: usize, changes: &[SubmitEntry], retried: usize) {
+    output::emit(&Record::SourceSummary {
+        source_id: source.id,
+        dry_run,
+        first_run,
+        discovered,
+        new: changes.iter().filter(|e| matches!(e.reason, SubmitReason::New)).count(),
+        modified: changes.iter().filter(|e| matches!(e.reason, SubmitReason::Modified { .. })).count(),
+        retried,
+        removed: changes.iter().filter(|e| matches!(e.reason, SubmitReason::Removed)).count(),
+    });
 }
 
-fn handle_subsequent_run_unattended(
-    conn: &rusqlite::Connection,
-    source: &db::Source,
-    entries: &[UrlEntry],
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Get stored URLs with their dates for this source
-    let stored_urls = db::get_urls_with_dates_for_source(conn, source.id)?;
+/// Emit a record for each URL that would be or is about to be submitted
+fn emit_urls(source: &db::Source, changes: &[SubmitEntry], retry_urls: &[(String, Vec<String>)], dry_run: bool) {
+    if !output::is_structured() {
+        return;
+    }
+    let endpoints: Vec<&str> = source.endpoints.iter().map(String::as_str).collect();
+    for entry in changes {
+        output::emit(&Record::url(source.id, entry, endpoints.clone(), dry_run));
+    }
+    for (url, retry_endpoints) in retry_urls {
+        output::emit(&Record::Url {
+            source_id: source.id,
+            url,
+            reason: SubmitReason::Retry.kind(),
+            modified: None,
+            endpoints: retry_endpoints.iter().map(String::as_str).collect(),
+            dry_run,
+        });
+    }
+}
 
+/// Compare fetched entries against stored URLs and return the new URLs and
+/// the URLs whose date changed
+fn collect_changes(entries: &[UrlEntry], stored_urls: &HashMap<String, db::StoredUrl>) -> Vec<SubmitEntry> {
     let mut to_submit: Vec<SubmitEntry> = Vec::new();
-    let mut new_count = 0;
-    let mut modified_count = 0;
+    let mut seen: HashSet<&str> = HashSet::new();
 
     for entry in entries {
-        if let Some(stored_date) = stored_urls.get(&entry.url) {
-            // URL exists in database - check if it was modified
-            if let Some(new_date) = &entry.date {
-                let is_modified = match stored_date {
-                    Some(old_date) => new_date != old_date,
-                    None => true, // No previous date, treat as modified
-                };
+        if !seen.insert(entry.url.as_str()) {
+            continue;
+        }
 
-                if is_modified {
-                    to_submit.push(SubmitEntry {
-                        url: entry.url.clone(),
-                        reason: SubmitReason::Modified {
-                            date: new_date.clone(),
-                        },
-                    });
-                    modified_count += 1;
-                }
+        if let Some(stored) = stored_urls.get(&entry.url) {
+            // URL exists in database - check if it was modified
+            let is_modified = match (&entry.date, &stored.last_modified) {
+                (Some(new_date), Some(old_date)) => new_date != old_date,
+                (Some(_), None) => true, // No previous date, treat as modified
+                (None, _) => false,
+            };
+
+            if is_modified {
+                to_submit.push(SubmitEntry {
+                    url: entry.url.clone(),
+                    reason: SubmitReason::Modified {
+                        date: entry.date.clone().unwrap_or_default(),
+                    },
+                });
             }
         } else {
             // New URL - not in database
@@ -925,76 +1514,162 @@ fn handle_subsequent_run_unattended(
                 url: entry.url.clone(),
                 reason: SubmitReason::New,
             });
-            new_count += 1;
         }
     }
 
-    if to_submit.is_empty() {
-        println!(
-            "  {} No new or modified URLs to submit. All URLs are up to date.",
-            "✓".green().bold()
-        );
-        return Ok(());
-    }
+    to_submit
+}
 
-    println!(
-        "\n  {} Found {} URL(s) to submit: {} new, {} modified",
-        "ℹ".cyan().bold(),
-        to_submit.len(),
-        new_count,
-        modified_count
-    );
+/// URLs to submit to a single IndexNow endpoint
+struct EndpointPlan {
+    endpoint: String,
+    entries: Vec<SubmitEntry>,
+}
 
-    // List URLs to be submitted
-    if new_count > 0 {
-        println!("\n  {} ({}):", "New URLs".green().bold(), new_count);
-        for entry in to_submit.iter().filter(|e| matches!(e.reason, SubmitReason::New)).take(5) {
-            println!("    • {}", entry.url);
-        }
-        if new_count > 5 {
-            println!("    {} ... and {} more", "".dimmed(), new_count - 5);
+/// Build the submissions for each endpoint of a source: every change goes to
+/// every endpoint, plus the URLs from earlier runs that endpoint never accepted
+/// (even if they have since dropped out of the feed).
+fn plan_submissions(
+    conn: &rusqlite::Connection,
+    source: &db::Source,
+    changes: &[SubmitEntry],
+) -> Result<Vec<EndpointPlan>, Box<dyn std::error::Error>> {
+    let changed: HashSet<&str> = changes.iter().map(|e| e.url.as_str()).collect();
+    let mut plans = Vec::new();
+
+    for endpoint in &source.endpoints {
+        let mut entries = changes.to_vec();
+        for url in db::get_unsubmitted_urls_for_endpoint(conn, source.id, endpoint)? {
+            if !changed.contains(url.as_str()) {
+                entries.push(SubmitEntry {
+                    url,
+                    reason: SubmitReason::Retry,
+                });
+            }
         }
+        plans.push(EndpointPlan {
+            endpoint: endpoint.clone(),
+            entries,
+        });
     }
-    if modified_count > 0 {
-        println!("\n  {} ({}):", "Modified URLs".yellow().bold(), modified_count);
-        for entry in to_submit.iter().filter(|e| matches!(e.reason, SubmitReason::Modified { .. })).take(5) {
-            if let SubmitReason::Modified { date } = &entry.reason {
-                println!("    • {} (updated: {})", entry.url, date.cyan());
+
+    Ok(plans)
+}
+
+/// Retried URLs across all plans, with the endpoints each one is retried on
+fn retry_targets(plans: &[EndpointPlan]) -> Vec<(String, Vec<String>)> {
+    let mut targets: Vec<(String, Vec<String>)> = Vec::new();
+    for plan in plans {
+        for entry in plan.entries.iter().filter(|e| matches!(e.reason, SubmitReason::Retry)) {
+            match targets.iter_mut().find(|(url, _)| *url == entry.url) {
+                Some((_, endpoints)) => endpoints.push(plan.endpoint.clone()),
+                None => targets.push((entry.url.clone(), vec![plan.endpoint.clone()])),
             }
         }
-        if modified_count > 5 {
-            println!("    {} ... and {} more", "".dimmed(), modified_count - 5);
-        }
     }
+    targets
+}
 
-    // Unattended mode: submit without confirmation
-    println!();
-    println!(
-        "  {} Unattended mode: Submitting {} URL(s) to {}...\n",
-        "→".blue().bold(),
-        to_submit.len(),
-        source.searchengine
-    );
-
-    submit::submit_in_batches(&source.api_key, &source.host, &source.searchengine, &to_submit)?;
+/// Record new and modified URLs as pending on every endpoint, along with their
+/// new dates. Removed URLs are marked as such and pending on every endpoint too.
+fn record_pending(
+    conn: &rusqlite::Connection,
+    source: &db::Source,
+    entries: &[UrlEntry],
+    changes: &[SubmitEntry],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dates: HashMap<&str, Option<&str>> = entries
+        .iter()
+        .map(|e| (e.url.as_str(), e.date.as_deref()))
+        .collect();
 
-    // Update database with submitted URLs
-    for entry in &to_submit {
+    let mut removed: Vec<&str> = Vec::new();
+    for entry in changes {
         let date = match &entry.reason {
-            SubmitReason::New => entries
-                .iter()
-                .find(|e| e.url == entry.url)
-                .and_then(|e| e.date.as_deref()),
             SubmitReason::Modified { date } => Some(date.as_str()),
+            SubmitReason::Removed => {
+                removed.push(&entry.url);
+                continue;
+            }
+            _ => dates.get(entry.url.as_str()).copied().flatten(),
         };
-        db::add_url_with_date_for_source(conn, source.id, &entry.url, date)?;
+        db::record_url_for_source(conn, source.id, &entry.url, date, UrlStatus::Pending)?;
     }
+    db::mark_urls_removed(conn, source.id, &removed)?;
 
-    println!(
-        "\n  {} Successfully submitted and stored {} URL(s).",
-        "✓".green().bold(),
-        to_submit.len()
-    );
+    let urls: Vec<&str> = changes.iter().map(|e| e.url.as_str()).collect();
+    db::record_pending_endpoints(conn, source.id, &urls, &source.endpoints)?;
 
     Ok(())
 }
+
+/// Submit each endpoint's URLs. A failing endpoint does not stop the others.
+fn submit_to_endpoints(
+    conn: &rusqlite::Connection,
+    source: &db::Source,
+    plans: &[EndpointPlan],
+    options: &RunOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failed: Vec<&str> = Vec::new();
+
+    for plan in plans.iter().filter(|p| !p.entries.is_empty()) {
+        say!(
+            "\n  {} Submitting {} URL(s) to {}...\n",
+            "→".blue().bold(),
+            plan.entries.len(),
+            plan.endpoint
+        );
+
+        if let Err(e) = queue::submit_or_queue(conn, source, &plan.endpoint, &plan.entries, options.max_attempts) {
+            say!("\n  {} {}: {}", "✗".red().bold(), plan.endpoint, e);
+            failed.push(&plan.endpoint);
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(format!("Submission failed on {}", failed.join(", ")).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(last_modified: Option<&str>) -> db::StoredUrl {
+        db::StoredUrl {
+            last_modified: last_modified.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_collect_changes() {
+        let entries = vec![
+            UrlEntry { url: "https://example.com/new".into(), date: None },
+            UrlEntry { url: "https://example.com/same".into(), date: Some("2026-01-01".into()) },
+            UrlEntry { url: "https://example.com/changed".into(), date: Some("2026-02-01".into()) },
+            UrlEntry { url: "https://example.com/undated".into(), date: Some("2026-01-01".into()) },
+            UrlEntry { url: "https://example.com/new".into(), date: None },
+        ];
+        let mut stored_urls = HashMap::new();
+        stored_urls.insert("https://example.com/same".to_string(), stored(Some("2026-01-01")));
+        stored_urls.insert("https://example.com/changed".to_string(), stored(Some("2026-01-01")));
+        stored_urls.insert("https://example.com/undated".to_string(), stored(None));
+
+        let to_submit = collect_changes(&entries, &stored_urls);
+        let summary: Vec<(&str, String)> = to_submit
+            .iter()
+            .map(|e| (e.url.as_str(), e.reason.to_string()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("https://example.com/new", "new".to_string()),
+                ("https://example.com/changed", "modified on 2026-02-01".to_string()),
+                ("https://example.com/undated", "modified on 2026-01-01".to_string()),
+            ]
+        );
+    }
+}
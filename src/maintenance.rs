@@ -0,0 +1,232 @@
+//! Scoped database maintenance: resetting, pruning and forgetting tracked URLs, and vacuuming
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, Source};
+use crate::filter::{self, Filter, Rule, RuleKind};
+use crate::output::{self, say, Record};
+use crate::queue;
+use colored::*;
+use dialoguer::Confirm;
+use std::io::IsTerminal;
+use std::path::Path;
+
+/// A maintenance operation on the tracked URLs of some sources
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// Forget every URL of the sources so their next run is a first run
+    Reset,
+    /// Forget URLs the sources haven't listed for this many days
+    Prune { days: u32 },
+    /// Forget URLs matching a filter pattern (glob or `re:` regex)
+    Forget { pattern: String },
+}
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Reset => "reset",
+            Action::Prune { .. } => "prune",
+            Action::Forget { .. } => "forget",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Action::Reset => "reset",
+            Action::Prune { .. } => "pruned",
+            Action::Forget { .. } => "forgotten",
+        }
+    }
+}
+
+/// Apply an action to the given sources: show what it affects, then ask
+/// before changing anything unless `yes` is set. A dry run only shows the counts.
+pub fn run(
+    db_path: &Path,
+    sources: &[Source],
+    action: &Action,
+    dry_run: bool,
+    yes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let matcher = match action {
+        Action::Forget { pattern } => {
+            filter::validate_pattern(pattern).map_err(|e| format!("--forget: {}", e))?;
+            Some(Filter::new(&[Rule { kind: RuleKind::Include, pattern: pattern.clone() }])?)
+        }
+        _ => None,
+    };
+    let cutoff = match action {
+        Action::Prune { days } => queue::unix_now() - i64::from(*days) * 86_400,
+        _ => 0,
+    };
+    let conn = db::init_db(db_path)?;
+
+    let mut plan: Vec<(&Source, Vec<String>)> = Vec::new();
+    for source in sources {
+        let urls: Vec<String> = db::get_tracked_urls(&conn, source.id)?
+            .into_iter()
+            .filter(|(url, last_seen)| match action {
+                Action::Reset => true,
+                Action::Prune { .. } => *last_seen < cutoff,
+                Action::Forget { .. } => matcher.as_ref().is_some_and(|m| m.allows(url)),
+            })
+            .map(|(url, _)| url)
+            .collect();
+        output::emit(&Record::Maintenance {
+            action: action.name(),
+            source_id: Some(source.id),
+            urls: Some(urls.len()),
+            reclaimable_bytes: None,
+            dry_run,
+        });
+
+        say!(
+            "{} [ID {}] {} URL(s) of {} {} {}.",
+            "→".blue().bold(),
+            source.id.to_string().bold(),
+            urls.len(),
+            source.source_url,
+            if dry_run { "would be" } else { "will be" },
+            action.past_tense()
+        );
+        if !matches!(action, Action::Reset) {
+            for url in urls.iter().take(5) {
+                say!("    • {}", url);
+            }
+            if urls.len() > 5 {
+                say!("    {} ... and {} more", "".dimmed(), urls.len() - 5);
+            }
+        }
+
+        // A reset also starts the first run over, even with nothing tracked
+        if !urls.is_empty() || (matches!(action, Action::Reset) && source.first_run_completed) {
+            plan.push((source, urls));
+        }
+    }
+
+    let total: usize = plan.iter().map(|(_, urls)| urls.len()).sum();
+    if plan.is_empty() {
+        say!("\n{} Nothing to {}.", "ℹ".cyan().bold(), action.name());
+        return Ok(());
+    }
+    if dry_run {
+        say!("\n{} Dry run: nothing was changed.", "ℹ".cyan().bold());
+        return Ok(());
+    }
+    if !confirm(&format!("{} {} URL(s) of {} source(s)?", capitalize(action.name()), total, plan.len()), yes)? {
+        say!("{} Operation cancelled.", "ℹ".cyan().bold());
+        return Ok(());
+    }
+
+    for (source, urls) in &plan {
+        match action {
+            Action::Reset => db::reset_source(&conn, source.id)?,
+            _ => {
+                let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+                db::forget_urls(&conn, source.id, &urls)?;
+            }
+        }
+    }
+    say!(
+        "{} {} {} URL(s).",
+        "✓".green().bold(),
+        capitalize(action.past_tense()),
+        total
+    );
+    if matches!(action, Action::Reset) {
+        say!(
+            "{} The next run of each reset source is treated as a first run.",
+            "ℹ".cyan().bold()
+        );
+    }
+    Ok(())
+}
+
+/// Rebuild the database file to give back the space of deleted rows
+pub fn vacuum(db_path: &Path, dry_run: bool, yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = db::init_db(db_path)?;
+    let (size, reclaimable) = db::database_size(&conn)?;
+    output::emit(&Record::Maintenance {
+        action: "vacuum",
+        source_id: None,
+        urls: None,
+        reclaimable_bytes: Some(reclaimable),
+        dry_run,
+    });
+    say!(
+        "{} Database is {}, of which {} can be reclaimed.",
+        "ℹ".cyan().bold(),
+        format_size(size),
+        format_size(reclaimable)
+    );
+
+    if dry_run {
+        return Ok(());
+    }
+    if !confirm("Vacuum the database?", yes)? {
+        say!("{} Operation cancelled.", "ℹ".cyan().bold());
+        return Ok(());
+    }
+    db::vacuum(&conn)?;
+    let (after, _) = db::database_size(&conn)?;
+    say!(
+        "{} Vacuumed: {} → {}.",
+        "✓".green().bold(),
+        format_size(size),
+        format_size(after)
+    );
+    Ok(())
+}
+
+fn confirm(prompt: &str, yes: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    if yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err("Not running in a terminal, so there is no one to confirm. Pass --yes to proceed.".into());
+    }
+    Ok(Confirm::new().with_prompt(prompt).default(false).interact()?)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(4096), "4.0 KB");
+        assert_eq!(format_size(3 * 1_048_576 / 2), "1.5 MB");
+        assert_eq!(capitalize("forget"), "Forget");
+    }
+}
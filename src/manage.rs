@@ -0,0 +1,409 @@
+//! Non-interactive source management for scripts and provisioning
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::config::{self, SourceType};
+use crate::db::{self, SourceHost};
+use crate::filter::{self, Rule};
+use crate::hosts;
+use crate::keyfile;
+use crate::normalize::{TrailingSlash, UrlNormalization};
+use crate::output::say;
+use colored::*;
+use std::path::Path;
+use url::Url;
+
+/// Source settings given on the command line. `None` fields are left
+/// unchanged when editing.
+#[derive(Debug, Default)]
+pub struct SourceArgs {
+    pub source_type: Option<SourceType>,
+    pub url: Option<String>,
+    pub key: Option<String>,
+    pub host: Option<String>,
+    /// Key file URL; `Some(None)` removes it so the key is expected at the host root
+    pub key_location: Option<Option<String>>,
+    pub endpoints: Option<Vec<String>>,
+    /// Seconds between polls in watch mode
+    pub poll_interval: Option<i64>,
+    /// URL filter rules replacing the current ones
+    pub filters: Option<Vec<Rule>>,
+    /// Additional hosts replacing the current ones
+    pub extra_hosts: Option<Vec<SourceHost>>,
+    pub trailing_slash: Option<TrailingSlash>,
+    pub strip_tracking: Option<bool>,
+    pub sort_query: Option<bool>,
+    /// Seconds a URL must be missing before it is submitted as removed;
+    /// `Some(None)` stops submitting removals
+    pub removal_grace: Option<Option<i64>>,
+    /// Only submit missing URLs as removed if they answer 404 or 410
+    pub removal_probe: Option<bool>,
+    /// Probe the source URL over the network before saving it
+    pub validate: bool,
+}
+
+impl SourceArgs {
+    /// Whether any source setting was given, i.e. the command should not prompt
+    pub fn is_empty(&self) -> bool {
+        self.source_type.is_none()
+            && self.url.is_none()
+            && self.key.is_none()
+            && self.host.is_none()
+            && self.key_location.is_none()
+            && self.endpoints.is_none()
+            && self.poll_interval.is_none()
+            && self.filters.is_none()
+            && self.extra_hosts.is_none()
+            && self.removal_grace.is_none()
+            && self.removal_probe.is_none()
+            && !self.changes_normalization()
+    }
+
+    fn changes_normalization(&self) -> bool {
+        self.trailing_slash.is_some() || self.strip_tracking.is_some() || self.sort_query.is_some()
+    }
+
+    /// The given normalization settings applied on top of `current`
+    fn normalization(&self, current: UrlNormalization) -> UrlNormalization {
+        UrlNormalization {
+            trailing_slash: self.trailing_slash.unwrap_or(current.trailing_slash),
+            strip_tracking: self.strip_tracking.unwrap_or(current.strip_tracking),
+            sort_query: self.sort_query.unwrap_or(current.sort_query),
+        }
+    }
+}
+
+/// Category of a non-interactive failure, reported as a stable code and exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A required argument is missing or a value is malformed
+    InvalidArgument,
+    /// The source URL failed validation
+    ValidationFailed,
+    /// No source has the given ID
+    NotFound,
+    /// Another source already uses the URL
+    AlreadyExists,
+    /// Database or other unexpected failure
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidArgument => "invalid-argument",
+            ErrorKind::ValidationFailed => "validation-failed",
+            ErrorKind::NotFound => "not-found",
+            ErrorKind::AlreadyExists => "already-exists",
+            ErrorKind::Internal => "internal",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Internal => 1,
+            ErrorKind::InvalidArgument => 2,
+            ErrorKind::ValidationFailed => 3,
+            ErrorKind::NotFound => 4,
+            ErrorKind::AlreadyExists => 5,
+        }
+    }
+}
+
+/// Error from a non-interactive command. Displayed on a single line as
+/// `error[<code>]: <message>` so scripts can match on the code.
+#[derive(Debug)]
+pub struct ManageError {
+    pub kind: ErrorKind,
+    message: String,
+}
+
+impl ManageError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        // Validation messages carry indented hints on extra lines; keep one line
+        let message: String = message.into();
+        let message = message
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        ManageError { kind, message }
+    }
+}
+
+impl std::fmt::Display for ManageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error[{}]: {}", self.kind.code(), self.message)
+    }
+}
+
+impl std::error::Error for ManageError {}
+
+impl From<rusqlite::Error> for ManageError {
+    fn from(e: rusqlite::Error) -> Self {
+        ManageError::new(ErrorKind::Internal, format!("database error: {}", e))
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for ManageError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        ManageError::new(ErrorKind::Internal, e.to_string())
+    }
+}
+
+/// Add a source from command-line arguments. `--url` and `--key` are required;
+/// the host defaults to the URL's host and the engine to `api.indexnow.org`.
+pub fn add_source(db_path: &Path, args: &SourceArgs) -> Result<i64, ManageError> {
+    let source_type = args.source_type.unwrap_or_default();
+    let url = args
+        .url
+        .as_deref()
+        .ok_or_else(|| ManageError::new(ErrorKind::InvalidArgument, "--url is required to add a source"))?;
+    let key = required_value(args.key.as_deref(), "--key")?
+        .ok_or_else(|| ManageError::new(ErrorKind::InvalidArgument, "--key is required to add a source"))?;
+    check_key(key)?;
+
+    check_filters(args.filters.as_deref())?;
+    let url = check_url(url, source_type, args.validate)?;
+    let conn = db::init_db(db_path)?;
+    if db::source_exists(&conn, &url)? {
+        return Err(ManageError::new(ErrorKind::AlreadyExists, format!("source already exists: {}", url)));
+    }
+
+    let host = match required_value(args.host.as_deref(), "--host")? {
+        Some(host) => host.to_string(),
+        None => Url::parse(&url)
+            .ok()
+            .and_then(|u| u.host_str().map(String::from))
+            .ok_or_else(|| ManageError::new(ErrorKind::InvalidArgument, "--host is required for this URL"))?,
+    };
+    let endpoints = endpoints_or_default(args.endpoints.as_deref())?;
+    let key_location = args.key_location.clone().flatten();
+    check_key_location(key_location.as_deref(), &host)?;
+    check_extra_hosts(&host, args.extra_hosts.as_deref().unwrap_or_default())?;
+
+    let id = db::add_source(&conn, source_type.as_str(), &url, key, &host, &endpoints)?;
+    if key_location.is_some() {
+        db::set_source_key_location(&conn, id, key_location.as_deref())?;
+    }
+    if args.poll_interval.is_some() {
+        db::set_source_poll_interval(&conn, id, args.poll_interval)?;
+    }
+    if let Some(rules) = &args.filters {
+        db::set_source_filters(&conn, id, rules)?;
+    }
+    if let Some(extra_hosts) = &args.extra_hosts {
+        db::set_source_extra_hosts(&conn, id, extra_hosts)?;
+    }
+    if args.changes_normalization() {
+        db::set_source_normalization(&conn, id, &args.normalization(UrlNormalization::default()))?;
+    }
+    if args.removal_grace.is_some() || args.removal_probe.is_some() {
+        db::set_source_removals(
+            &conn,
+            id,
+            args.removal_grace.flatten(),
+            args.removal_probe.unwrap_or(true),
+        )?;
+    }
+    say!(
+        "{} Source added with ID {}.",
+        "✓".green().bold(),
+        id
+    );
+    Ok(id)
+}
+
+/// Update the given fields of a source. The URL is validated again only if
+/// it or the source type changes.
+pub fn edit_source(db_path: &Path, id: Option<i64>, args: &SourceArgs) -> Result<(), ManageError> {
+    let id = id.ok_or_else(|| ManageError::new(ErrorKind::InvalidArgument, "--id is required to edit a source"))?;
+    if args.is_empty() {
+        return Err(ManageError::new(
+            ErrorKind::InvalidArgument,
+            "nothing to change: give at least one of --type, --url, --key, --host, --key-location, --no-key-location, --engine, --interval, --include, --exclude, --no-filters, --extra-host, --no-extra-hosts, --trailing-slash, --strip-tracking, --sort-query, --removal-grace, --no-removals, --probe-removals",
+        ));
+    }
+    check_filters(args.filters.as_deref())?;
+
+    let conn = db::init_db(db_path)?;
+    let source = find_source(&conn, id)?;
+
+    let current_type = SourceType::from_db(&source.source_type);
+    let source_type = args.source_type.unwrap_or(current_type);
+    let url = match (&args.url, source_type != current_type) {
+        (Some(url), _) => check_url(url, source_type, args.validate)?,
+        (None, true) => check_url(&source.source_url, source_type, args.validate)?,
+        (None, false) => source.source_url.clone(),
+    };
+    if url != source.source_url && db::source_exists(&conn, &url)? {
+        return Err(ManageError::new(ErrorKind::AlreadyExists, format!("source already exists: {}", url)));
+    }
+
+    let key = match required_value(args.key.as_deref(), "--key")? {
+        Some(key) => {
+            check_key(key)?;
+            key
+        }
+        None => &source.api_key,
+    };
+    let host = required_value(args.host.as_deref(), "--host")?.unwrap_or(&source.host);
+    let key_location = args.key_location.clone().unwrap_or_else(|| source.key_location.clone());
+    check_key_location(key_location.as_deref(), host)?;
+    check_extra_hosts(host, args.extra_hosts.as_deref().unwrap_or(&source.extra_hosts))?;
+    let endpoints = match args.endpoints.as_deref() {
+        Some(endpoints) => endpoints_or_default(Some(endpoints))?,
+        None => source.endpoints.clone(),
+    };
+
+    db::update_source(&conn, id, source_type.as_str(), &url, key, host, &endpoints)?;
+    if key_location != source.key_location {
+        db::set_source_key_location(&conn, id, key_location.as_deref())?;
+    }
+    if args.poll_interval.is_some() {
+        db::set_source_poll_interval(&conn, id, args.poll_interval)?;
+    }
+    if let Some(rules) = &args.filters {
+        db::set_source_filters(&conn, id, rules)?;
+    }
+    if let Some(extra_hosts) = &args.extra_hosts {
+        db::set_source_extra_hosts(&conn, id, extra_hosts)?;
+    }
+    let removal_grace = args.removal_grace.unwrap_or(source.removal_grace);
+    let removal_probe = args.removal_probe.unwrap_or(source.removal_probe);
+    if removal_grace != source.removal_grace || removal_probe != source.removal_probe {
+        db::set_source_removals(&conn, id, removal_grace, removal_probe)?;
+    }
+    let normalization = args.normalization(source.normalization);
+    if normalization != source.normalization {
+        let rewritten = db::set_source_normalization(&conn, id, &normalization)?;
+        if rewritten > 0 {
+            say!(
+                "{} Normalized {} tracked URL(s).",
+                "ℹ".cyan().bold(),
+                rewritten
+            );
+        }
+    }
+    say!(
+        "{} Source {} updated.",
+        "✓".green().bold(),
+        id
+    );
+    Ok(())
+}
+
+/// Remove a source and its tracked URLs without asking for confirmation
+pub fn remove_source(db_path: &Path, id: Option<i64>) -> Result<(), ManageError> {
+    let id = id.ok_or_else(|| ManageError::new(ErrorKind::InvalidArgument, "--id is required to remove a source"))?;
+    let conn = db::init_db(db_path)?;
+    if !db::remove_source(&conn, id)? {
+        return Err(ManageError::new(ErrorKind::NotFound, format!("no source with ID {}", id)));
+    }
+    say!(
+        "{} Source {} removed.",
+        "✓".green().bold(),
+        id
+    );
+    Ok(())
+}
+
+fn find_source(conn: &rusqlite::Connection, id: i64) -> Result<db::Source, ManageError> {
+    db::get_all_sources(conn)?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| ManageError::new(ErrorKind::NotFound, format!("no source with ID {}", id)))
+}
+
+/// Normalize a source URL, probing it unless validation is disabled
+fn check_url(url: &str, source_type: SourceType, validate: bool) -> Result<String, ManageError> {
+    let result = if validate {
+        config::validate_source_url(url, source_type, false)
+    } else {
+        config::normalize_source_url(url, source_type)
+    };
+    result.map_err(|e| ManageError::new(ErrorKind::ValidationFailed, e))
+}
+
+/// Reject filter patterns that don't compile
+fn check_filters(rules: Option<&[Rule]>) -> Result<(), ManageError> {
+    for rule in rules.unwrap_or_default() {
+        filter::validate_pattern(&rule.pattern)
+            .map_err(|e| ManageError::new(ErrorKind::InvalidArgument, format!("--{}: {}", rule.kind.as_str(), e)))?;
+    }
+    Ok(())
+}
+
+/// Reject a key location that is not a URL on the source's host
+fn check_key(key: &str) -> Result<(), ManageError> {
+    keyfile::validate_key(key).map_err(|e| ManageError::new(ErrorKind::InvalidArgument, format!("--key: {}", e)))
+}
+
+fn check_key_location(key_location: Option<&str>, host: &str) -> Result<(), ManageError> {
+    match key_location {
+        Some(location) => hosts::validate_key_location(location, host)
+            .map_err(|e| ManageError::new(ErrorKind::InvalidArgument, format!("--key-location: {}", e))),
+        None => Ok(()),
+    }
+}
+
+/// Reject extra hosts that repeat the source's host or each other
+fn check_extra_hosts(host: &str, extra_hosts: &[SourceHost]) -> Result<(), ManageError> {
+    for (i, extra) in extra_hosts.iter().enumerate() {
+        if extra.host.eq_ignore_ascii_case(host) || extra_hosts[..i].iter().any(|h| h.host == extra.host) {
+            return Err(ManageError::new(
+                ErrorKind::InvalidArgument,
+                format!("--extra-host: {} is listed more than once", extra.host),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Reject values that were given but are empty
+fn required_value<'a>(value: Option<&'a str>, flag: &str) -> Result<Option<&'a str>, ManageError> {
+    match value.map(str::trim) {
+        Some("") => Err(ManageError::new(ErrorKind::InvalidArgument, format!("{} must not be empty", flag))),
+        other => Ok(other),
+    }
+}
+
+fn endpoints_or_default(endpoints: Option<&[String]>) -> Result<Vec<String>, ManageError> {
+    match endpoints {
+        Some([]) => Err(ManageError::new(ErrorKind::InvalidArgument, "--engine must list at least one host")),
+        Some(endpoints) => Ok(endpoints.to_vec()),
+        None => Ok(vec![config::DEFAULT_ENDPOINT.to_string()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_is_reported_on_one_line() {
+        let err = ManageError::new(
+            ErrorKind::ValidationFailed,
+            "URL returned HTTP 404 - Not Found\n    Please verify the URL exists and is publicly accessible.",
+        );
+        assert_eq!(
+            err.to_string(),
+            "error[validation-failed]: URL returned HTTP 404 - Not Found Please verify the URL exists and is publicly accessible."
+        );
+        assert_eq!(err.kind.exit_code(), 3);
+    }
+}
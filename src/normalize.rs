@@ -0,0 +1,224 @@
+//! URL normalization applied to discovered URLs before they are compared and stored
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::feed::UrlEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use url::Url;
+
+/// Query parameters added by analytics and ad platforms. Entries ending in `_`
+/// are prefixes.
+const TRACKING_PARAMS: &[&str] = &[
+    "utm_", "fbclid", "gclid", "gclsrc", "dclid", "gbraid", "wbraid", "msclkid", "yclid",
+    "twclid", "ttclid", "igshid", "mc_cid", "mc_eid", "_ga", "_gl", "_hsenc", "_hsmi", "mkt_tok",
+];
+
+/// What to do with the trailing slash of a URL path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    /// Leave paths as the source lists them
+    #[default]
+    Keep,
+    /// Append a slash to paths whose last segment has no file extension
+    Add,
+    /// Remove the trailing slash, except from the root path
+    Remove,
+}
+
+impl TrailingSlash {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrailingSlash::Keep => "keep",
+            TrailingSlash::Add => "add",
+            TrailingSlash::Remove => "remove",
+        }
+    }
+
+    /// Parse a stored policy; unknown values keep slashes as they are
+    pub fn from_db(value: &str) -> Self {
+        value.parse().unwrap_or_default()
+    }
+}
+
+impl std::str::FromStr for TrailingSlash {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "keep" => Ok(TrailingSlash::Keep),
+            "add" => Ok(TrailingSlash::Add),
+            "remove" => Ok(TrailingSlash::Remove),
+            other => Err(format!("expected 'keep', 'add' or 'remove', got '{}'", other)),
+        }
+    }
+}
+
+/// Normalization settings of a source.
+///
+/// Host case, default ports and fragments are always normalized; the rest is
+/// configurable because some sites serve different pages for those variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct UrlNormalization {
+    pub trailing_slash: TrailingSlash,
+    /// Drop `utm_*`, `fbclid`, `gclid` and similar query parameters
+    pub strip_tracking: bool,
+    /// Sort query parameters by name
+    pub sort_query: bool,
+}
+
+impl Default for UrlNormalization {
+    fn default() -> Self {
+        UrlNormalization {
+            trailing_slash: TrailingSlash::Keep,
+            strip_tracking: true,
+            // Off by default: many sites treat `?b=2&a=1` and `?a=1&b=2` as different pages
+            sort_query: false,
+        }
+    }
+}
+
+impl std::fmt::Display for UrlNormalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} trailing slash", self.trailing_slash.as_str())?;
+        if self.strip_tracking {
+            write!(f, ", strip tracking params")?;
+        }
+        if self.sort_query {
+            write!(f, ", sort query")?;
+        }
+        Ok(())
+    }
+}
+
+impl UrlNormalization {
+    /// Normalize one URL. URLs that don't parse are returned unchanged.
+    pub fn apply(&self, url: &str) -> String {
+        // Parsing lowercases the host and drops the scheme's default port
+        let Ok(mut parsed) = Url::parse(url.trim()) else {
+            return url.to_string();
+        };
+        parsed.set_fragment(None);
+
+        if let Some(query) = parsed.query().map(str::to_string) {
+            let mut params: Vec<&str> = query
+                .split('&')
+                .filter(|p| !p.is_empty())
+                .filter(|p| !(self.strip_tracking && is_tracking_param(p)))
+                .collect();
+            if self.sort_query {
+                // Stable, so repeated parameters keep their relative order
+                params.sort_by_key(|p| p.split('=').next().unwrap_or_default());
+            }
+            let query = params.join("&");
+            parsed.set_query(if query.is_empty() { None } else { Some(&query) });
+        }
+
+        let path = parsed.path();
+        match self.trailing_slash {
+            TrailingSlash::Keep => {}
+            TrailingSlash::Add => {
+                let last = path.rsplit('/').next().unwrap_or_default();
+                if !path.ends_with('/') && !last.contains('.') {
+                    let path = format!("{}/", path);
+                    parsed.set_path(&path);
+                }
+            }
+            TrailingSlash::Remove => {
+                if path.len() > 1 && path.ends_with('/') {
+                    let path = path.trim_end_matches('/').to_string();
+                    parsed.set_path(if path.is_empty() { "/" } else { &path });
+                }
+            }
+        }
+
+        parsed.to_string()
+    }
+
+    /// Normalize fetched entries, merging URLs that become identical. The first
+    /// occurrence is kept and takes the date of a later one if it has none.
+    pub fn apply_all(&self, entries: Vec<UrlEntry>) -> Vec<UrlEntry> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut normalized: Vec<UrlEntry> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let url = self.apply(&entry.url);
+            match seen.get(&url) {
+                Some(&index) => {
+                    if normalized[index].date.is_none() {
+                        normalized[index].date = entry.date;
+                    }
+                }
+                None => {
+                    seen.insert(url.clone(), normalized.len());
+                    normalized.push(UrlEntry { url, date: entry.date });
+                }
+            }
+        }
+        normalized
+    }
+}
+
+fn is_tracking_param(param: &str) -> bool {
+    let name = param.split('=').next().unwrap_or_default().to_ascii_lowercase();
+    TRACKING_PARAMS.iter().any(|tracking| {
+        if tracking.ends_with('_') {
+            name.starts_with(tracking)
+        } else {
+            name == *tracking
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url() {
+        let default = UrlNormalization::default();
+        assert_eq!(
+            default.apply("HTTPS://Example.COM:443/Post/?b=2&utm_source=feed&a=1#comments"),
+            "https://example.com/Post/?b=2&a=1"
+        );
+        let sorted = UrlNormalization { sort_query: true, ..default };
+        assert_eq!(sorted.apply("https://example.com/p?b=2&a=1&b=1"), "https://example.com/p?a=1&b=2&b=1");
+        assert_eq!(default.apply("http://example.com:80?fbclid=x"), "http://example.com/");
+        assert_eq!(default.apply("https://example.com:8443/p?_ga=1&page=2"), "https://example.com:8443/p?page=2");
+        assert_eq!(default.apply("not a url"), "not a url");
+
+        let add = UrlNormalization { trailing_slash: TrailingSlash::Add, ..default };
+        assert_eq!(add.apply("https://example.com/post?x=1"), "https://example.com/post/?x=1");
+        assert_eq!(add.apply("https://example.com/file.pdf"), "https://example.com/file.pdf");
+
+        let remove = UrlNormalization {
+            trailing_slash: TrailingSlash::Remove,
+            strip_tracking: false,
+            sort_query: false,
+        };
+        assert_eq!(remove.apply("https://example.com/post/?b=1&utm_medium=x"), "https://example.com/post?b=1&utm_medium=x");
+        assert_eq!(remove.apply("https://example.com/"), "https://example.com/");
+
+        let entries = vec![
+            UrlEntry { url: "https://example.com/a/".to_string(), date: None },
+            UrlEntry { url: "https://EXAMPLE.com/a".to_string(), date: Some("2026-03-01".to_string()) },
+        ];
+        let merged = remove.apply_all(entries);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].url, "https://example.com/a");
+        assert_eq!(merged[0].date.as_deref(), Some("2026-03-01"));
+    }
+}
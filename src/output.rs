@@ -0,0 +1,292 @@
+//! Machine-readable output: JSON / NDJSON records on stdout, human text on stderr
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::filter::Rule;
+use crate::normalize::UrlNormalization;
+use crate::submit::{SubmitEntry, SubmitReason};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+
+/// Format of everything written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored human-readable text
+    #[default]
+    Text,
+    /// One JSON array of all records, written when the command finishes
+    Json,
+    /// One JSON record per line, written as soon as it is produced
+    Ndjson,
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+static BUFFER: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Set the output format for the rest of the process. Called once from `main`.
+pub fn init(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Whether stdout carries JSON records instead of human-readable text
+pub fn is_structured() -> bool {
+    matches!(FORMAT.get(), Some(OutputFormat::Json | OutputFormat::Ndjson))
+}
+
+/// Print human-readable progress: to stdout normally, or to stderr when stdout
+/// carries JSON records so it stays parseable.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::output::is_structured() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use say;
+
+/// A structured output record, tagged with its `type`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record<'a> {
+    /// A configured source (`--list`, `--show`)
+    Source {
+        id: i64,
+        source_type: &'a str,
+        url: &'a str,
+        host: &'a str,
+        /// Masked IndexNow key
+        key: String,
+        /// Key file URL, `null` if the key is at the host root
+        key_location: Option<&'a str>,
+        endpoints: &'a [String],
+        first_run_completed: bool,
+        /// Seconds between polls in watch mode, `null` for the default
+        poll_interval: Option<i64>,
+        /// Seconds a URL must be missing before it is submitted as removed,
+        /// `null` if removals are off
+        removal_grace: Option<i64>,
+        /// Whether missing URLs must answer 404 or 410 to count as removed
+        removal_probe: bool,
+        /// Include/exclude rules in the order they are applied
+        filters: &'a [Rule],
+        normalization: UrlNormalization,
+        /// Other hosts the source submits URLs for, with masked keys
+        extra_hosts: Vec<HostRecord<'a>>,
+        /// URL count per endpoint and submission state (`--show` only)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        url_states: Option<BTreeMap<String, BTreeMap<&'static str, i64>>>,
+    },
+    /// What was found when a source was fetched and compared with the database
+    SourceSummary {
+        source_id: i64,
+        dry_run: bool,
+        first_run: bool,
+        discovered: usize,
+        new: usize,
+        modified: usize,
+        retried: usize,
+        /// URLs submitted as removed after dropping out of the source
+        removed: usize,
+    },
+    /// URLs dropped by the include/exclude rules of a source
+    Filtered {
+        source_id: i64,
+        dropped: usize,
+        /// URLs that matched none of the include rules
+        not_included: usize,
+        /// URLs dropped by each exclude pattern
+        excluded: BTreeMap<&'a str, usize>,
+    },
+    /// URLs kept out of submissions because they are on none of the source's hosts
+    OffHost {
+        source_id: i64,
+        /// Hosts the source submits for
+        hosts: Vec<&'a str>,
+        urls: Vec<&'a str>,
+        /// In a dry run the URLs are reported but not quarantined
+        dry_run: bool,
+    },
+    /// A fetched IndexNow key file (`--verify`, and before submitting)
+    KeyCheck {
+        source_id: i64,
+        host: &'a str,
+        url: &'a str,
+        /// HTTP status of the last response, `null` if the request failed
+        status: Option<u16>,
+        redirects: Vec<&'a str>,
+        content_type: Option<&'a str>,
+        /// Submission is blocked for the source until this is true
+        passed: bool,
+        problems: &'a [String],
+        warnings: &'a [String],
+    },
+    /// A URL that would be (dry run) or is about to be submitted
+    Url {
+        source_id: i64,
+        url: &'a str,
+        /// `new`, `modified`, `retry`, `removed` or `forced`
+        reason: &'static str,
+        /// New date of a modified URL
+        #[serde(skip_serializing_if = "Option::is_none")]
+        modified: Option<&'a str>,
+        endpoints: Vec<&'a str>,
+        dry_run: bool,
+    },
+    /// One request to an IndexNow endpoint
+    Batch {
+        source_id: Option<i64>,
+        endpoint: &'a str,
+        host: &'a str,
+        urls: Vec<&'a str>,
+        /// HTTP status, or `null` if no response was received
+        status: Option<u16>,
+        accepted: bool,
+        duration_ms: u128,
+        response_body: &'a str,
+    },
+    /// A logged IndexNow request (`--history`)
+    History {
+        id: i64,
+        source_id: Option<i64>,
+        endpoint: &'a str,
+        /// `null` for requests logged by older versions
+        host: Option<&'a str>,
+        url_count: i64,
+        status: Option<u16>,
+        response_body: Option<&'a str>,
+        duration_ms: i64,
+        reason: &'a str,
+        created_at: &'a str,
+        urls: Vec<&'a str>,
+    },
+    /// A queued or dead-lettered submission (`--queue`)
+    Queued {
+        source_id: i64,
+        endpoint: &'a str,
+        url: &'a str,
+        reason: &'a str,
+        attempts: u32,
+        next_attempt_at: i64,
+        last_error: Option<&'a str>,
+        dead_lettered: bool,
+    },
+    /// A key generated with `--keygen`
+    Key {
+        key: &'a str,
+        /// Key file written with `--key-dir`
+        file: Option<&'a str>,
+        /// Source the key was attached to with `--id`
+        source_id: Option<i64>,
+    },
+    /// What a maintenance command changed, or would change in a dry run
+    Maintenance {
+        /// `reset`, `prune`, `forget` or `vacuum`
+        action: &'static str,
+        /// `null` for `vacuum`, which works on the whole database
+        source_id: Option<i64>,
+        /// Tracked URLs affected
+        #[serde(skip_serializing_if = "Option::is_none")]
+        urls: Option<usize>,
+        /// Bytes `vacuum` can give back
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reclaimable_bytes: Option<u64>,
+        dry_run: bool,
+    },
+    /// The command failed
+    Error { message: String },
+}
+
+/// An additional host of a source in a `source` record
+#[derive(Debug, Serialize)]
+pub struct HostRecord<'a> {
+    pub host: &'a str,
+    pub key: String,
+    pub key_location: Option<&'a str>,
+}
+
+impl<'a> Record<'a> {
+    /// Record for a URL about to be submitted to the given endpoints
+    pub fn url(source_id: i64, entry: &'a SubmitEntry, endpoints: Vec<&'a str>, dry_run: bool) -> Self {
+        let modified = match &entry.reason {
+            SubmitReason::Modified { date } => Some(date.as_str()),
+            _ => None,
+        };
+        Record::Url {
+            source_id,
+            url: &entry.url,
+            reason: entry.reason.kind(),
+            modified,
+            endpoints,
+            dry_run,
+        }
+    }
+}
+
+/// Write a record: immediately as one line for NDJSON, buffered for JSON,
+/// and not at all for text output.
+pub fn emit(record: &Record) {
+    let format = FORMAT.get().copied().unwrap_or_default();
+    if format == OutputFormat::Text {
+        return;
+    }
+    let Ok(line) = serde_json::to_string(record) else {
+        return;
+    };
+
+    if format == OutputFormat::Ndjson {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+        let _ = stdout.flush();
+    } else {
+        BUFFER.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+    }
+}
+
+/// Write the buffered records as one JSON array, one record per line.
+/// Called once before the process exits.
+pub fn finish() {
+    if FORMAT.get() != Some(&OutputFormat::Json) {
+        return;
+    }
+    let records = std::mem::take(&mut *BUFFER.lock().unwrap_or_else(|e| e.into_inner()));
+    if records.is_empty() {
+        println!("[]");
+    } else {
+        println!("[\n  {}\n]", records.join(",\n  "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_record() {
+        let entry = SubmitEntry {
+            url: "https://example.com/post".to_string(),
+            reason: SubmitReason::Modified { date: "2026-03-01".to_string() },
+        };
+        let record = Record::url(7, &entry, vec!["www.bing.com"], true);
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"type":"url","source_id":7,"url":"https://example.com/post","reason":"modified","modified":"2026-03-01","endpoints":["www.bing.com"],"dry_run":true}"#
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! Persistent retry queue for submissions that failed with a retryable error
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, QueuedSubmission, UrlStatus};
+use crate::hosts;
+use crate::output::{self, say, Record};
+use crate::submit::{self, BatchReport, SubmitEntry, SubmitError, SubmitReason};
+use colored::*;
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default number of attempts before a queued submission is dead-lettered
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry; doubled after every failed attempt
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Upper bound for the exponential backoff
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Delay before the next attempt: the endpoint's `Retry-After` if it sent one,
+/// otherwise exponential backoff based on the number of attempts so far
+pub fn retry_delay(attempts: u32, retry_after: Option<Duration>) -> Duration {
+    if let Some(delay) = retry_after {
+        return delay.min(MAX_RETRY_DELAY);
+    }
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    BASE_RETRY_DELAY.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+/// Submit URLs for a source to one endpoint, marking each batch as submitted
+/// for that endpoint once it accepts it.
+///
+/// If the endpoint is currently backing off, the URLs are queued without contacting it.
+/// A batch that fails with a retryable error (429, 5xx, network error) is queued; a batch
+/// rejected with any other error is marked as failed, so the next run retries it, and the
+/// first such error is returned.
+pub fn submit_or_queue(
+    conn: &Connection,
+    source: &db::Source,
+    endpoint: &str,
+    to_submit: &[SubmitEntry],
+    max_attempts: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let now = unix_now();
+    let to_submit = &on_host_entries(conn, source, to_submit)?;
+    if to_submit.is_empty() {
+        return Ok(());
+    }
+    let items: Vec<QueuedSubmission> = to_submit
+        .iter()
+        .map(|e| QueuedSubmission {
+            source_id: source.id,
+            endpoint: endpoint.to_string(),
+            url: e.url.clone(),
+            reason: e.reason.kind().to_string(),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            dead_lettered: false,
+        })
+        .collect();
+
+    if let Some(until) = db::get_backoff_until(conn, source.id, endpoint, now)? {
+        let waiting: Vec<&QueuedSubmission> = items.iter().collect();
+        let queued = hold(conn, &waiting, until, "Endpoint backing off after earlier failures")?;
+        say!(
+            "  {} {} is backing off after earlier failures. Queued {} URL(s) for retry in {}.",
+            "⚠".yellow().bold(),
+            endpoint,
+            queued,
+            format_delay(until - now)
+        );
+        return Ok(());
+    }
+
+    let mut outcome = Outcome::default();
+    let result = submit::submit_in_batches(
+        &source.hosts(),
+        endpoint,
+        to_submit,
+        |report| record_batch(conn, source.id, report, &mut outcome),
+    );
+
+    match result {
+        Ok(()) => {
+            say!(
+                "\n  {} Successfully submitted and stored {} URL(s).",
+                "✓".green().bold(),
+                to_submit.len()
+            );
+            return Ok(());
+        }
+        Err(e) if e.downcast_ref::<SubmitError>().is_none() => {
+            let urls: Vec<&str> = items
+                .iter()
+                .map(|q| q.url.as_str())
+                .filter(|url| !outcome.accepted.contains(*url))
+                .collect();
+            db::set_urls_status(conn, source.id, endpoint, &urls, UrlStatus::Failed)?;
+            return Err(e);
+        }
+        Err(_) => {}
+    }
+
+    if !outcome.accepted.is_empty() {
+        say!(
+            "\n  {} {} URL(s) were accepted before the failure.",
+            "ℹ".cyan().bold(),
+            outcome.accepted.len()
+        );
+    }
+
+    let fatal = reschedule_rejected(conn, &items, &outcome, max_attempts)?;
+    let untried = outcome.untried(&items);
+    if !untried.is_empty() {
+        hold(conn, &untried, now, "Not tried after an earlier batch for the host failed")?;
+    }
+    match fatal {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// Retry queued submissions for a source that are due, on each of its endpoints.
+/// Called before the source's feed or sitemap is processed on every run; a rejected
+/// retry is reported but never keeps the source from being processed.
+pub fn drain(
+    conn: &Connection,
+    source: &db::Source,
+    max_attempts: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for endpoint in &source.endpoints {
+        drain_endpoint(conn, source, endpoint, max_attempts)?;
+    }
+    Ok(())
+}
+
+fn drain_endpoint(
+    conn: &Connection,
+    source: &db::Source,
+    endpoint: &str,
+    max_attempts: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let due: Vec<QueuedSubmission> = db::get_due_submissions(conn, source.id, endpoint, unix_now())?;
+    let (due, off_host): (Vec<QueuedSubmission>, Vec<QueuedSubmission>) = due
+        .into_iter()
+        .partition(|q| hosts::is_allowed(&q.url, &source.hosts()));
+    quarantine(conn, source, off_host.iter().map(|q| q.url.as_str()).collect())?;
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    say!(
+        "  {} Retrying {} queued URL(s) on {}...\n",
+        "↻".cyan().bold(),
+        due.len(),
+        endpoint
+    );
+
+    let entries: Vec<SubmitEntry> = due
+        .iter()
+        .map(|q| SubmitEntry {
+            url: q.url.clone(),
+            reason: SubmitReason::Retry,
+        })
+        .collect();
+
+    let mut outcome = Outcome::default();
+    let result = submit::submit_in_batches(
+        &source.hosts(),
+        endpoint,
+        &entries,
+        |report| record_batch(conn, source.id, report, &mut outcome),
+    );
+
+    match result {
+        Ok(()) => {
+            say!(
+                "\n  {} Successfully resubmitted {} queued URL(s).\n",
+                "✓".green().bold(),
+                due.len()
+            );
+        }
+        Err(e) if e.downcast_ref::<SubmitError>().is_none() => return Err(e),
+        // URLs that were not tried stay in the queue as they are
+        Err(_) => {
+            if let Some(error) = reschedule_rejected(conn, &due, &outcome, max_attempts)? {
+                say!(
+                    "\n  {} {}: {}. The rejected URLs were marked as failed and are retried on the next run.",
+                    "✗".red().bold(),
+                    endpoint,
+                    error
+                );
+            }
+            say!();
+        }
+    }
+    Ok(())
+}
+
+/// Accepted URLs and rejected batches of one submission
+#[derive(Default)]
+struct Outcome {
+    accepted: HashSet<String>,
+    rejected: Vec<(HashSet<String>, SubmitError)>,
+}
+
+impl Outcome {
+    /// Items that were neither accepted nor rejected: the batches skipped after
+    /// an earlier batch of the same host failed
+    fn untried<'a>(&self, items: &'a [QueuedSubmission]) -> Vec<&'a QueuedSubmission> {
+        items
+            .iter()
+            .filter(|q| !self.accepted.contains(&q.url) && !self.rejected.iter().any(|(urls, _)| urls.contains(&q.url)))
+            .collect()
+    }
+}
+
+/// Keep the entries on the source's hosts. The others are quarantined, since a
+/// single off-host URL makes IndexNow reject the whole bulk request with 422.
+fn on_host_entries(
+    conn: &Connection,
+    source: &db::Source,
+    entries: &[SubmitEntry],
+) -> Result<Vec<SubmitEntry>, Box<dyn std::error::Error>> {
+    let (on_host, off_host): (Vec<SubmitEntry>, Vec<SubmitEntry>) = entries
+        .iter()
+        .cloned()
+        .partition(|e| hosts::is_allowed(&e.url, &source.hosts()));
+    quarantine(conn, source, off_host.iter().map(|e| e.url.as_str()).collect())?;
+    Ok(on_host)
+}
+
+fn quarantine(conn: &Connection, source: &db::Source, urls: Vec<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    if !urls.is_empty() {
+        hosts::report_off_host(&source.hosts(), &urls, 3, false);
+        db::quarantine_urls(conn, source.id, &urls)?;
+    }
+    Ok(())
+}
+
+/// Log a request and, if the endpoint accepted it, mark its URLs as submitted
+fn record_batch(
+    conn: &Connection,
+    source_id: i64,
+    report: &BatchReport,
+    outcome: &mut Outcome,
+) -> Result<(), Box<dyn std::error::Error>> {
+    db::log_submission(conn, Some(source_id), report)?;
+    output::emit(&Record::Batch {
+        source_id: Some(source_id),
+        endpoint: report.endpoint,
+        host: report.host,
+        urls: report.entries.iter().map(|e| e.url.as_str()).collect(),
+        status: report.status,
+        accepted: report.accepted(),
+        duration_ms: report.duration.as_millis(),
+        response_body: &report.response_body,
+    });
+    if report.accepted() {
+        let urls: Vec<&str> = report.entries.iter().map(|e| e.url.as_str()).collect();
+        db::mark_urls_submitted(conn, source_id, report.endpoint, &urls)?;
+        outcome.accepted.extend(report.entries.iter().map(|e| e.url.clone()));
+    } else if let Some(error) = report.error {
+        let urls = report.entries.iter().map(|e| e.url.clone()).collect();
+        outcome.rejected.push((urls, error.clone()));
+    }
+    Ok(())
+}
+
+/// Queue URLs without counting an attempt, keeping the attempts of URLs that are
+/// already queued. Returns how many were queued; dead-lettered URLs stay dead-lettered.
+fn hold(
+    conn: &Connection,
+    items: &[&QueuedSubmission],
+    next_attempt_at: i64,
+    reason: &str,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut queued = 0;
+    for item in items {
+        let stored = db::get_queued_submission(conn, item.source_id, &item.endpoint, &item.url)?;
+        let dead_lettered = stored.as_ref().is_some_and(|q| q.dead_lettered);
+        db::enqueue_submission(conn, &QueuedSubmission {
+            next_attempt_at,
+            last_error: Some(reason.to_string()),
+            ..(*item).clone()
+        })?;
+        let status = if dead_lettered { UrlStatus::DeadLettered } else { UrlStatus::Queued };
+        db::set_urls_status(conn, item.source_id, &item.endpoint, &[item.url.as_str()], status)?;
+        if !dead_lettered {
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+/// Reschedule each rejected batch with its own error. Returns the first error
+/// that is not worth retrying.
+fn reschedule_rejected(
+    conn: &Connection,
+    items: &[QueuedSubmission],
+    outcome: &Outcome,
+    max_attempts: u32,
+) -> Result<Option<SubmitError>, Box<dyn std::error::Error>> {
+    let mut fatal = None;
+    for (urls, error) in &outcome.rejected {
+        let batch: Vec<&QueuedSubmission> = items.iter().filter(|q| urls.contains(&q.url)).collect();
+        if !reschedule(conn, &batch, error, max_attempts)? {
+            fatal.get_or_insert_with(|| error.clone());
+        }
+    }
+    Ok(fatal)
+}
+
+/// Put the URLs of a rejected batch back in the queue with backoff, dead-lettering
+/// those that reached `max_attempts`. If the error is not worth retrying they are
+/// marked as failed instead and `false` is returned.
+fn reschedule(
+    conn: &Connection,
+    batch: &[&QueuedSubmission],
+    error: &SubmitError,
+    max_attempts: u32,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if !error.is_retryable() {
+        for item in batch {
+            db::dequeue_submissions(conn, item.source_id, &item.endpoint, &[item.url.as_str()])?;
+            db::set_urls_status(conn, item.source_id, &item.endpoint, &[item.url.as_str()], UrlStatus::Failed)?;
+        }
+        return Ok(false);
+    }
+
+    let now = unix_now();
+    let mut queued = 0;
+    let mut dead = 0;
+    let mut next_attempt_at = now;
+
+    for item in batch {
+        // A URL submitted again while it is queued keeps counting from its stored attempts
+        let stored = db::get_queued_submission(conn, item.source_id, &item.endpoint, &item.url)?
+            .map_or(0, |q| q.attempts);
+        let attempts = item.attempts.max(stored) + 1;
+        let dead_lettered = attempts >= max_attempts;
+        let next = now + retry_delay(attempts, error.retry_after).as_secs() as i64;
+
+        db::enqueue_submission(conn, &QueuedSubmission {
+            attempts,
+            next_attempt_at: next,
+            last_error: Some(error.to_string()),
+            dead_lettered,
+            ..(*item).clone()
+        })?;
+        let status = if dead_lettered { UrlStatus::DeadLettered } else { UrlStatus::Queued };
+        db::set_urls_status(conn, item.source_id, &item.endpoint, &[item.url.as_str()], status)?;
+
+        if dead_lettered {
+            dead += 1;
+        } else {
+            queued += 1;
+            next_attempt_at = next_attempt_at.max(next);
+        }
+    }
+
+    if queued > 0 {
+        say!(
+            "\n  {} {}. Queued {} URL(s) for retry in {}.",
+            "⚠".yellow().bold(),
+            error,
+            queued,
+            format_delay(next_attempt_at - now)
+        );
+    }
+    if dead > 0 {
+        say!(
+            "  {} Gave up on {} URL(s) after {} attempts. Run '{} --queue' to inspect them.",
+            "✗".red().bold(),
+            dead,
+            max_attempts,
+            env!("CARGO_PKG_NAME")
+        );
+    }
+
+    Ok(true)
+}
+
+/// Format a number of seconds as `42s`, `15m` or `2h 5m`
+pub fn format_delay(secs: i64) -> String {
+    let secs = secs.max(0);
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86_400 {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    } else {
+        format!("{}d {}h", secs / 86_400, (secs % 86_400) / 3600)
+    }
+}
+
+/// Show queued and dead-lettered submissions
+pub fn show_queue(db_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = db::init_db(db_path)?;
+    let items = db::get_queued_submissions(&conn)?;
+
+    if output::is_structured() {
+        for item in &items {
+            output::emit(&Record::Queued {
+                source_id: item.source_id,
+                endpoint: &item.endpoint,
+                url: &item.url,
+                reason: &item.reason,
+                attempts: item.attempts,
+                next_attempt_at: item.next_attempt_at,
+                last_error: item.last_error.as_deref(),
+                dead_lettered: item.dead_lettered,
+            });
+        }
+        return Ok(());
+    }
+
+    say!(
+        "{} Retry Queue",
+        "═".repeat(40).blue().bold()
+    );
+
+    if items.is_empty() {
+        say!("\n{} The retry queue is empty.", "✓".green().bold());
+        return Ok(());
+    }
+
+    let now = unix_now();
+    let (dead, queued): (Vec<&QueuedSubmission>, Vec<&QueuedSubmission>) =
+        items.iter().partition(|q| q.dead_lettered);
+
+    if !queued.is_empty() {
+        say!("\n{} ({}):", "Queued".yellow().bold(), queued.len());
+        for item in &queued {
+            let due = if item.next_attempt_at <= now {
+                "due now".to_string()
+            } else {
+                format!("next attempt in {}", format_delay(item.next_attempt_at - now))
+            };
+            say!(
+                "  [ID {}] {} → {} ({}, {} attempt(s), {})",
+                item.source_id,
+                item.url,
+                item.endpoint.dimmed(),
+                item.reason,
+                item.attempts,
+                due.cyan()
+            );
+            if let Some(err) = &item.last_error {
+                say!("     {}", err.dimmed());
+            }
+        }
+    }
+
+    if !dead.is_empty() {
+        say!("\n{} ({}):", "Dead-lettered".red().bold(), dead.len());
+        for item in &dead {
+            say!(
+                "  [ID {}] {} → {} ({}, {} attempt(s))",
+                item.source_id,
+                item.url,
+                item.endpoint.dimmed(),
+                item.reason,
+                item.attempts
+            );
+            if let Some(err) = &item.last_error {
+                say!("     {}", err.dimmed());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        assert_eq!(retry_delay(1, None), Duration::from_secs(300));
+        assert_eq!(retry_delay(2, None), Duration::from_secs(600));
+        assert_eq!(retry_delay(4, None), Duration::from_secs(2400));
+        assert_eq!(retry_delay(30, None), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(3, Some(Duration::from_secs(42))), Duration::from_secs(42));
+    }
+
+    #[test]
+    fn test_requeue_keeps_attempts() {
+        let conn = db::init_db(Path::new(db::IN_MEMORY)).unwrap();
+        let endpoints = vec!["api.indexnow.org".to_string()];
+        let id = db::add_source(&conn, "sitemap", "https://example.com/sitemap.xml", "key", "example.com", &endpoints).unwrap();
+        let item = QueuedSubmission {
+            source_id: id,
+            endpoint: endpoints[0].clone(),
+            url: "https://example.com/a".to_string(),
+            reason: "new".to_string(),
+            attempts: 3,
+            next_attempt_at: 0,
+            last_error: None,
+            dead_lettered: false,
+        };
+        let stored = |conn: &Connection| db::get_queued_submission(conn, id, &item.endpoint, &item.url).unwrap().unwrap();
+
+        db::enqueue_submission(&conn, &item).unwrap();
+        let again = QueuedSubmission { attempts: 0, ..item.clone() };
+        assert_eq!(hold(&conn, &[&again], 0, "backing off").unwrap(), 1);
+        assert_eq!(stored(&conn).attempts, 3);
+
+        db::enqueue_submission(&conn, &QueuedSubmission { attempts: 5, dead_lettered: true, ..item.clone() }).unwrap();
+        assert_eq!(hold(&conn, &[&again], 0, "backing off").unwrap(), 0);
+        assert_eq!(stored(&conn).attempts, 5);
+        assert!(stored(&conn).dead_lettered);
+    }
+}
@@ -0,0 +1,195 @@
+//! Removal of URLs that dropped out of a source, so search engines recrawl them
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, Source};
+use crate::feed::UrlEntry;
+use crate::filter::Filter;
+use crate::hosts;
+use crate::output::say;
+use crate::queue;
+use crate::submit::{SubmitEntry, SubmitReason};
+use colored::*;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// What a missing URL answered when it was probed
+#[derive(Debug, PartialEq, Eq)]
+enum Probe {
+    /// 404 Not Found or 410 Gone
+    Gone,
+    /// Any other response: the page still exists outside the source
+    Live(u16),
+    /// No response, so it is probed again on the next run
+    Unreachable,
+}
+
+/// Note which tracked URLs a source still lists and return the ones that have
+/// been missing for longer than its grace period, to be submitted as removed.
+///
+/// URLs the source's filter rules now reject or that are on none of its hosts
+/// are left alone: they were dropped on purpose, not deleted. With probing on,
+/// only URLs answering 404 or 410 are returned; live ones count as seen again.
+/// A dry run records nothing.
+pub fn find(
+    conn: &Connection,
+    source: &Source,
+    entries: &[UrlEntry],
+    dry_run: bool,
+) -> Result<Vec<SubmitEntry>, Box<dyn std::error::Error>> {
+    let listed: Vec<&str> = entries.iter().map(|e| e.url.as_str()).collect();
+    if !dry_run {
+        db::mark_urls_seen(conn, source.id, &listed)?;
+    }
+    let Some(grace) = source.removal_grace else {
+        return Ok(Vec::new());
+    };
+
+    let listed: HashSet<&str> = listed.into_iter().collect();
+    let filter = Filter::new(&source.filters)
+        .map_err(|e| format!("Invalid filter rule for source {}: {}", source.id, e))?;
+    let source_hosts = source.hosts();
+    let missing: Vec<String> = db::get_unseen_urls(conn, source.id, queue::unix_now() - grace)?
+        .into_iter()
+        .filter(|url| !listed.contains(url.as_str()))
+        .filter(|url| filter.allows(url) && hosts::is_allowed(url, &source_hosts))
+        .collect();
+    if missing.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let removed = if source.removal_probe {
+        confirm_gone(conn, source, missing, dry_run)?
+    } else {
+        missing
+    };
+    Ok(removed
+        .into_iter()
+        .map(|url| SubmitEntry {
+            url,
+            reason: SubmitReason::Removed,
+        })
+        .collect())
+}
+
+/// Probe missing URLs and keep the ones that are gone. Live URLs are marked as
+/// seen so they are not probed again until another grace period has passed.
+fn confirm_gone(
+    conn: &Connection,
+    source: &Source,
+    missing: Vec<String>,
+    dry_run: bool,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    say!(
+        "  {} Checking {} URL(s) missing from the source for over {}...",
+        "→".blue().bold(),
+        missing.len(),
+        queue::format_delay(source.removal_grace.unwrap_or_default())
+    );
+    let client = build_client()?;
+    let mut gone = Vec::new();
+    let mut live: Vec<(String, u16)> = Vec::new();
+    let mut unreachable = 0;
+
+    for url in missing {
+        match probe(&client, &url) {
+            Probe::Gone => gone.push(url),
+            Probe::Live(status) => live.push((url, status)),
+            Probe::Unreachable => unreachable += 1,
+        }
+    }
+
+    if !live.is_empty() {
+        say!(
+            "  {} {} missing URL(s) still answer and {} kept:",
+            "ℹ".cyan().bold(),
+            live.len(),
+            if dry_run { "would be" } else { "were" }
+        );
+        for (url, status) in live.iter().take(5) {
+            say!("    • {} {}", url, format!("({})", status).dimmed());
+        }
+        if live.len() > 5 {
+            say!("    {} ... and {} more", "".dimmed(), live.len() - 5);
+        }
+        if !dry_run {
+            let urls: Vec<&str> = live.iter().map(|(url, _)| url.as_str()).collect();
+            db::mark_urls_seen(conn, source.id, &urls)?;
+        }
+    }
+    if unreachable > 0 {
+        say!(
+            "  {} {} missing URL(s) could not be reached and will be checked again on the next run.",
+            "⚠".yellow().bold(),
+            unreachable
+        );
+    }
+
+    Ok(gone)
+}
+
+/// Ask whether a URL still exists, with a HEAD request falling back to GET for
+/// servers that don't allow HEAD
+fn probe(client: &Client, url: &str) -> Probe {
+    let status = match client.head(url).send() {
+        Ok(response) if response.status() == StatusCode::METHOD_NOT_ALLOWED => {
+            client.get(url).send().map(|r| r.status())
+        }
+        response => response.map(|r| r.status()),
+    };
+    match status {
+        Ok(status) => classify(status),
+        Err(_) => Probe::Unreachable,
+    }
+}
+
+fn classify(status: StatusCode) -> Probe {
+    match status {
+        StatusCode::NOT_FOUND | StatusCode::GONE => Probe::Gone,
+        status if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => Probe::Unreachable,
+        status => Probe::Live(status.as_u16()),
+    }
+}
+
+fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
+    Ok(Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent(format!(
+            "{}/{} (+{})",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_REPOSITORY")
+        ))
+        .build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(StatusCode::NOT_FOUND), Probe::Gone);
+        assert_eq!(classify(StatusCode::GONE), Probe::Gone);
+        assert_eq!(classify(StatusCode::OK), Probe::Live(200));
+        assert_eq!(classify(StatusCode::FORBIDDEN), Probe::Live(403));
+        assert_eq!(classify(StatusCode::SERVICE_UNAVAILABLE), Probe::Unreachable);
+        assert_eq!(classify(StatusCode::TOO_MANY_REQUESTS), Probe::Unreachable);
+    }
+}
@@ -0,0 +1,170 @@
+//! Forced resubmission of stored URLs, for example after a site migration
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, Source};
+use crate::keyfile;
+use crate::output::{self, say, Record};
+use crate::queue;
+use crate::submit::{SubmitEntry, SubmitReason};
+use colored::*;
+use dialoguer::Confirm;
+use regex::Regex;
+use rusqlite::Connection;
+use std::path::Path;
+
+/// Which stored URLs to resubmit and how
+#[derive(Debug, Default)]
+pub struct ResubmitArgs {
+    /// Only URLs matching this regular expression
+    pub pattern: Option<String>,
+    /// Only URLs last submitted on or after this date (`YYYY-MM-DD`, UTC)
+    pub since: Option<String>,
+    /// Only URLs last submitted on or before this date (`YYYY-MM-DD`, UTC)
+    pub until: Option<String>,
+    pub dry_run: bool,
+    pub unattended: bool,
+    pub max_attempts: u32,
+}
+
+/// Submit the selected URLs of each source again to all of its engines,
+/// whether or not they changed. Accepted URLs get a new submission time;
+/// rejected ones go through the retry queue like any other submission.
+pub fn run(db_path: &Path, sources: &[Source], args: &ResubmitArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let pattern = args
+        .pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| format!("Invalid --match regex: {}", e))?;
+    let conn = db::init_db(db_path)?;
+
+    let mut selected: Vec<(&Source, Vec<SubmitEntry>)> = Vec::new();
+    for source in sources {
+        let entries = select(&conn, source, pattern.as_ref(), args)?;
+        say!(
+            "{} [ID {}] {} URL(s) of {} selected.",
+            "→".blue().bold(),
+            source.id.to_string().bold(),
+            entries.len(),
+            source.source_url
+        );
+        let endpoints: Vec<&str> = source.endpoints.iter().map(String::as_str).collect();
+        for entry in &entries {
+            output::emit(&Record::url(source.id, entry, endpoints.clone(), args.dry_run));
+        }
+        for entry in entries.iter().take(5) {
+            say!("    • {}", entry.url);
+        }
+        if entries.len() > 5 {
+            say!("    {} ... and {} more", "".dimmed(), entries.len() - 5);
+        }
+        if !entries.is_empty() {
+            selected.push((source, entries));
+        }
+    }
+
+    let total: usize = selected.iter().map(|(_, entries)| entries.len()).sum();
+    if total == 0 {
+        say!("\n{} No stored URLs match.", "ℹ".cyan().bold());
+        return Ok(());
+    }
+    if args.dry_run {
+        say!(
+            "\n{} Would resubmit {} URL(s). Run without -d to submit them.",
+            "ℹ".cyan().bold(),
+            total
+        );
+        return Ok(());
+    }
+
+    say!();
+    if !args.unattended {
+        let confirmed = Confirm::new()
+            .with_prompt(format!("  Resubmit {} URL(s) to IndexNow?", total))
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            say!("\n  {} Resubmission cancelled.", "ℹ".cyan().bold());
+            return Ok(());
+        }
+    }
+
+    let mut failed: Vec<i64> = Vec::new();
+    for (source, entries) in &selected {
+        if let Err(e) = resubmit_source(&conn, source, entries, args.max_attempts) {
+            say!("\n  {} [ID {}] {}", "✗".red().bold(), source.id, e);
+            failed.push(source.id);
+        }
+    }
+
+    if !failed.is_empty() {
+        let ids: Vec<String> = failed.iter().map(i64::to_string).collect();
+        return Err(format!("Resubmission failed for source(s) {}", ids.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Stored URLs of a source that match the filters. Quarantined and removed
+/// URLs are never resubmitted.
+fn select(
+    conn: &Connection,
+    source: &Source,
+    pattern: Option<&Regex>,
+    args: &ResubmitArgs,
+) -> Result<Vec<SubmitEntry>, Box<dyn std::error::Error>> {
+    let urls = db::get_urls_submitted_between(conn, source.id, args.since.as_deref(), args.until.as_deref())?;
+    Ok(urls
+        .into_iter()
+        .filter(|url| pattern.is_none_or(|re| re.is_match(url)))
+        .map(|url| SubmitEntry {
+            url,
+            reason: SubmitReason::Forced,
+        })
+        .collect())
+}
+
+fn resubmit_source(
+    conn: &Connection,
+    source: &Source,
+    entries: &[SubmitEntry],
+    max_attempts: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !keyfile::preflight(conn, source, false)? {
+        return Err("Key file check failed".into());
+    }
+    let urls: Vec<&str> = entries.iter().map(|e| e.url.as_str()).collect();
+    db::record_pending_endpoints(conn, source.id, &urls, &source.endpoints)?;
+
+    let mut failed: Vec<&str> = Vec::new();
+    for endpoint in &source.endpoints {
+        say!(
+            "\n  {} Resubmitting {} URL(s) of source {} to {}...\n",
+            "→".blue().bold(),
+            entries.len(),
+            source.id,
+            endpoint
+        );
+        if let Err(e) = queue::submit_or_queue(conn, source, endpoint, entries, max_attempts) {
+            say!("\n  {} {}: {}", "✗".red().bold(), endpoint, e);
+            failed.push(endpoint);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("Submission failed on {}", failed.join(", ")).into());
+    }
+    Ok(())
+}
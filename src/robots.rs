@@ -0,0 +1,122 @@
+//! Sitemap discovery from robots.txt `Sitemap:` directives
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::feed::UrlEntry;
+use crate::http_cache::{self, Fetched};
+use crate::output::say;
+use crate::sitemap;
+use colored::*;
+use rusqlite::Connection;
+use url::Url;
+
+/// Fetch a robots.txt and all URLs from the sitemaps it lists
+pub fn fetch_robots_urls(conn: &Connection, robots_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
+    let client = sitemap::build_client()?;
+
+    say!(
+        "  {} Fetching robots.txt: {}",
+        "→".blue(),
+        robots_url.dimmed()
+    );
+
+    let sitemaps = match http_cache::conditional_get(conn, &client, robots_url)? {
+        Fetched::NotModified(document) => document.sitemaps,
+        Fetched::Modified(response) => {
+            if !response.status().is_success() {
+                return Err(format!("Failed to fetch robots.txt: HTTP {}", response.status()).into());
+            }
+            let mut document = http_cache::new_document(&response);
+            document.sitemaps = parse_sitemap_directives(&response.text()?, robots_url);
+            http_cache::remember(conn, robots_url, &document)?;
+            document.sitemaps
+        }
+    };
+
+    if sitemaps.is_empty() {
+        say!(
+            "    {} No Sitemap: directives found in robots.txt",
+            "⚠".yellow()
+        );
+        return Ok(Vec::new());
+    }
+
+    say!(
+        "    {} Found {} sitemap(s) in robots.txt",
+        "ℹ".cyan(),
+        sitemaps.len()
+    );
+
+    sitemap::fetch_sitemaps(conn, &client, &sitemaps)
+}
+
+/// Extract the sitemap URLs from a robots.txt body.
+///
+/// Directive names are case-insensitive and may appear anywhere in the file.
+/// Relative URLs are resolved against the robots.txt URL and duplicates are dropped.
+fn parse_sitemap_directives(content: &str, robots_url: &str) -> Vec<String> {
+    let base = Url::parse(robots_url).ok();
+    let mut sitemaps: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if !name.trim().eq_ignore_ascii_case("sitemap") {
+            continue;
+        }
+
+        let value = value.trim();
+        let resolved = match &base {
+            Some(base) => base.join(value).map(String::from).ok(),
+            None => Url::parse(value).map(String::from).ok(),
+        };
+        if let Some(url) = resolved {
+            if !sitemaps.contains(&url) {
+                sitemaps.push(url);
+            }
+        }
+    }
+
+    sitemaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sitemap_directives() {
+        let robots = "User-agent: *\n\
+            Disallow: /admin/ # private\n\
+            Sitemap: https://example.com/sitemap.xml\n\
+            \n\
+            sitemap:https://example.com/news.xml.gz\n\
+            SITEMAP: /relative-sitemap.xml\n\
+            Sitemap: https://example.com/sitemap.xml\n\
+            # Sitemap: https://example.com/commented.xml\n";
+
+        assert_eq!(
+            parse_sitemap_directives(robots, "https://example.com/robots.txt"),
+            vec![
+                "https://example.com/sitemap.xml",
+                "https://example.com/news.xml.gz",
+                "https://example.com/relative-sitemap.xml",
+            ]
+        );
+    }
+}
@@ -1,39 +1,57 @@
 //! Sitemap parsing with recursive sitemap index support
 
-/// Copyright (C) 2026 Andre Franca <andre@abf.li>
-///
-/// This program is free software: you can redistribute it and/or modify
-/// it under the terms of the GNU Affero General Public License as published by
-/// the Free Software Foundation, either version 3 of the License, or
-/// (at your option) any later version.
-///
-/// This program is distributed in the hope that it will be useful,
-/// but WITHOUT ANY WARRANTY; without even the implied warranty of
-/// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
-/// GNU Affero General Public License for more details.
-///
-/// You should have received a copy of the GNU Affero General Public License
-/// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::feed::UrlEntry;
+use crate::http_cache::{self, Fetched};
+use crate::output::say;
 use colored::*;
-use regex::Regex;
+use flate2::bufread::GzDecoder;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::Reader;
 use reqwest::blocking::Client;
+use rusqlite::Connection;
 use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader};
 use std::time::Duration;
 
 /// Fetch all URLs from a sitemap, recursively handling sitemap indexes
-pub fn fetch_sitemap_urls(sitemap_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
-    let client = build_client()?;
+pub fn fetch_sitemap_urls(conn: &Connection, sitemap_url: &str) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
+    fetch_sitemaps(conn, &build_client()?, &[sitemap_url.to_string()])
+}
+
+/// Fetch all URLs from several sitemaps, keeping the first occurrence of URLs
+/// listed in more than one of them
+pub fn fetch_sitemaps(
+    conn: &Connection,
+    client: &Client,
+    sitemap_urls: &[String],
+) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
     let mut seen_urls: HashSet<String> = HashSet::new();
     let mut entries: Vec<UrlEntry> = Vec::new();
 
-    fetch_sitemap_recursive(&client, sitemap_url, &mut entries, &mut seen_urls, 0)?;
+    for sitemap_url in sitemap_urls {
+        fetch_sitemap_recursive(conn, client, sitemap_url, &mut entries, &mut seen_urls, 0)?;
+    }
 
     Ok(entries)
 }
 
-fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
+pub fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
     let user_agent = format!(
         "{}/{} (+{})",
         env!("CARGO_PKG_NAME"),
@@ -48,6 +66,7 @@ fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
 }
 
 fn fetch_sitemap_recursive(
+    conn: &Connection,
     client: &Client,
     url: &str,
     entries: &mut Vec<UrlEntry>,
@@ -57,7 +76,7 @@ fn fetch_sitemap_recursive(
     // Prevent infinite recursion
     const MAX_DEPTH: usize = 10;
     if depth > MAX_DEPTH {
-        println!(
+        say!(
             "  {} Maximum sitemap depth ({}) reached, skipping: {}",
             "⚠".yellow(),
             MAX_DEPTH,
@@ -66,119 +85,235 @@ fn fetch_sitemap_recursive(
         return Ok(());
     }
 
-    println!(
+    say!(
         "  {} Fetching sitemap: {}",
         "→".blue(),
         url.dimmed()
     );
 
-    let response = client.get(url).send()?;
+    let document = match http_cache::conditional_get(conn, client, url)? {
+        Fetched::NotModified(document) => {
+            say!(
+                "    {} Not modified since the last run, using {} known entries",
+                "ℹ".cyan(),
+                document.entries.len() + document.sitemaps.len()
+            );
+            document
+        }
+        Fetched::Modified(response) => {
+            if !response.status().is_success() {
+                return Err(format!("Failed to fetch sitemap: HTTP {}", response.status()).into());
+            }
 
-    if !response.status().is_success() {
-        return Err(format!("Failed to fetch sitemap: HTTP {}", response.status()).into());
-    }
+            // Stream the body through the parser instead of buffering the whole document
+            let mut document = http_cache::new_document(&response);
+            let body = decompress_if_gzip(BufReader::new(response), url)?;
+            parse_sitemap(body, |item| match item {
+                SitemapItem::Sitemap(sub_url) => document.sitemaps.push(sub_url),
+                SitemapItem::Url(entry) => document.entries.push(entry),
+            })?;
+            http_cache::remember(conn, url, &document)?;
+            document
+        }
+    };
 
-    let content = response.text()?;
+    let sub_sitemaps = document.sitemaps;
+    let found = document.entries.len();
+    let mut added = 0;
+    for entry in document.entries {
+        if seen_urls.insert(entry.url.clone()) {
+            entries.push(entry);
+            added += 1;
+        }
+    }
 
-    // Detect if this is a sitemap index or a regular sitemap
-    if content.contains("<sitemapindex") {
-        // This is a sitemap index - parse and recurse
-        let sub_sitemaps = parse_sitemap_index(&content)?;
-        println!(
+    if sub_sitemaps.is_empty() {
+        say!(
+            "    {} Found {} URLs (added {}, {} duplicates skipped)",
+            "✓".green(),
+            found,
+            added,
+            found - added
+        );
+    } else {
+        // This is a sitemap index - recurse into each sub-sitemap
+        say!(
             "    {} Found sitemap index with {} sub-sitemaps",
             "ℹ".cyan(),
             sub_sitemaps.len()
         );
 
         for sub_url in sub_sitemaps {
-            fetch_sitemap_recursive(client, &sub_url, entries, seen_urls, depth + 1)?;
+            fetch_sitemap_recursive(conn, client, &sub_url, entries, seen_urls, depth + 1)?;
         }
-    } else {
-        // This is a regular sitemap - parse URLs
-        let urls = parse_sitemap(&content)?;
-        let mut added = 0;
-
-        for entry in urls {
-            if seen_urls.insert(entry.url.clone()) {
-                entries.push(entry);
-                added += 1;
-            }
-        }
-
-        println!(
-            "    {} Found {} URLs (added {}, {} duplicates skipped)",
-            "✓".green(),
-            added + (entries.len() - added),
-            added,
-            entries.len() - added
-        );
     }
 
     Ok(())
 }
 
-/// Parse a sitemap index XML and return the list of sitemap URLs
-fn parse_sitemap_index(content: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let mut sitemaps = Vec::new();
-
-    // Use regex to find <sitemap>...<loc>URL</loc>...</sitemap> blocks
-    // The (?s) flag makes . match newlines
-    let sitemap_re = Regex::new(r"(?s)<sitemap[^>]*>.*?</sitemap>")?;
-    let loc_re = Regex::new(r"<loc>\s*([^<]+?)\s*</loc>")?;
-
-    for sitemap_match in sitemap_re.find_iter(content) {
-        let sitemap_block = sitemap_match.as_str();
-        if let Some(caps) = loc_re.captures(sitemap_block) {
-            if let Some(loc) = caps.get(1) {
-                let url = loc.as_str().trim().to_string();
-                if !url.is_empty() {
-                    sitemaps.push(url);
-                }
-            }
-        }
+/// Wrap the body in a gzip decoder if it is gzip-compressed.
+///
+/// Servers often send `.xml.gz` files as `application/x-gzip` without a
+/// `Content-Encoding` header, so the body is checked for the gzip magic bytes.
+/// The `.gz` extension is only used when the body is too short to tell.
+fn decompress_if_gzip<'a, R: BufRead + 'a>(mut reader: R, url: &str) -> io::Result<Box<dyn BufRead + 'a>> {
+    const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+    let head = reader.fill_buf()?;
+    let is_gzip = if head.len() >= GZIP_MAGIC.len() {
+        head.starts_with(&GZIP_MAGIC)
+    } else {
+        url.split(['?', '#']).next().unwrap_or(url).ends_with(".gz")
+    };
+
+    if is_gzip {
+        Ok(Box::new(BufReader::new(GzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
     }
+}
+
+/// An entry found while parsing a sitemap or sitemap index
+#[derive(Debug)]
+enum SitemapItem {
+    /// A `<url>` of a regular sitemap
+    Url(UrlEntry),
+    /// A `<sitemap>` of a sitemap index
+    Sitemap(String),
+}
 
-    Ok(sitemaps)
+/// Child element of `<url>` / `<sitemap>` whose text is being collected
+#[derive(Clone, Copy)]
+enum Field {
+    Loc,
+    Lastmod,
 }
 
-/// Parse a sitemap XML and return URL entries with lastmod dates
-fn parse_sitemap(content: &str) -> Result<Vec<UrlEntry>, Box<dyn std::error::Error>> {
-    let mut entries = Vec::new();
-
-    // Use regex to find <url>...<loc>URL</loc>...</url> blocks
-    // The (?s) flag makes . match newlines
-    let url_re = Regex::new(r"(?s)<url[^>]*>.*?</url>")?;
-    let loc_re = Regex::new(r"<loc>\s*([^<]+?)\s*</loc>")?;
-    let lastmod_re = Regex::new(r"<lastmod>\s*([^<]+?)\s*</lastmod>")?;
-
-    for url_match in url_re.find_iter(content) {
-        let url_block = url_match.as_str();
-        
-        if let Some(loc_caps) = loc_re.captures(url_block) {
-            if let Some(loc) = loc_caps.get(1) {
-                let url = loc.as_str().trim().to_string();
-                if !url.is_empty() {
-                    let lastmod = lastmod_re
-                        .captures(url_block)
-                        .and_then(|c| c.get(1))
-                        .map(|m| m.as_str().trim().to_string());
-
-                    entries.push(UrlEntry {
-                        url,
-                        date: lastmod,
-                    });
+/// Stream-parse a sitemap or sitemap index, calling `on_item` for every entry.
+///
+/// Elements are matched by local name, so namespace prefixes like `<sm:loc>` work.
+/// Only direct children of `<url>` and `<sitemap>` are read, which keeps extension
+/// elements such as `<image:loc>` from overriding the page URL. Entities and CDATA
+/// sections are decoded and comments are skipped.
+fn parse_sitemap<R: BufRead>(
+    source: R,
+    mut on_item: impl FnMut(SitemapItem),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = Reader::from_reader(source);
+    let mut buf = Vec::new();
+
+    let mut depth = 0usize;
+    // Depth and kind of the enclosing <url> or <sitemap>; true for <sitemap>
+    let mut entry: Option<(usize, bool)> = None;
+    let mut field: Option<Field> = None;
+    let mut loc = String::new();
+    let mut lastmod = String::new();
+
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(|e| {
+            format!("Invalid sitemap XML at byte {}: {}", reader.error_position(), e)
+        })?;
+
+        match event {
+            Event::Start(e) => {
+                depth += 1;
+                match (e.local_name().as_ref(), entry) {
+                    (name @ (b"url" | b"sitemap"), None) => {
+                        entry = Some((depth, name == b"sitemap"));
+                        loc.clear();
+                        lastmod.clear();
+                    }
+                    (b"loc", Some((d, _))) if depth == d + 1 => field = Some(Field::Loc),
+                    (b"lastmod", Some((d, _))) if depth == d + 1 => field = Some(Field::Lastmod),
+                    _ => {}
+                }
+            }
+            Event::End(_) => {
+                field = None;
+                if let Some((d, is_index)) = entry {
+                    if depth == d {
+                        entry = None;
+                        let url = loc.trim();
+                        if !url.is_empty() {
+                            on_item(if is_index {
+                                SitemapItem::Sitemap(url.to_string())
+                            } else {
+                                let date = Some(lastmod.trim()).filter(|d| !d.is_empty());
+                                SitemapItem::Url(UrlEntry {
+                                    url: url.to_string(),
+                                    date: date.map(String::from),
+                                })
+                            });
+                        }
+                    }
                 }
+                depth = depth.saturating_sub(1);
             }
+            Event::Text(t) => {
+                if let Some(f) = field {
+                    text_for(f, &mut loc, &mut lastmod).push_str(&t.xml10_content()?);
+                }
+            }
+            Event::CData(t) => {
+                if let Some(f) = field {
+                    text_for(f, &mut loc, &mut lastmod).push_str(&t.decode()?);
+                }
+            }
+            Event::GeneralRef(r) => {
+                if let Some(f) = field {
+                    let text = text_for(f, &mut loc, &mut lastmod);
+                    if let Some(ch) = r.resolve_char_ref()? {
+                        text.push(ch);
+                    } else {
+                        let name = r.decode()?;
+                        match resolve_predefined_entity(&name) {
+                            Some(value) => text.push_str(value),
+                            None => return Err(format!("Unknown entity '&{};' in sitemap", name).into()),
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
         }
+        buf.clear();
     }
 
-    Ok(entries)
+    Ok(())
 }
 
+fn text_for<'a>(field: Field, loc: &'a mut String, lastmod: &'a mut String) -> &'a mut String {
+    match field {
+        Field::Loc => loc,
+        Field::Lastmod => lastmod,
+    }
+}
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse_urls(xml: &str) -> Vec<UrlEntry> {
+        let mut entries = Vec::new();
+        parse_sitemap(xml.as_bytes(), |item| {
+            if let SitemapItem::Url(entry) = item {
+                entries.push(entry);
+            }
+        })
+        .unwrap();
+        entries
+    }
+
+    fn parse_index(xml: &str) -> Vec<String> {
+        let mut sitemaps = Vec::new();
+        parse_sitemap(xml.as_bytes(), |item| {
+            if let SitemapItem::Sitemap(url) = item {
+                sitemaps.push(url);
+            }
+        })
+        .unwrap();
+        sitemaps
+    }
+
     #[test]
     fn test_parse_sitemap() {
         let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
@@ -192,7 +327,7 @@ mod tests {
   </url>
 </urlset>"#;
 
-        let entries = parse_sitemap(xml).unwrap();
+        let entries = parse_urls(xml);
         assert_eq!(entries.len(), 2);
         assert_eq!(entries[0].url, "https://example.com/page1");
         assert_eq!(entries[0].date, Some("2026-01-15".to_string()));
@@ -212,9 +347,86 @@ mod tests {
   </sitemap>
 </sitemapindex>"#;
 
-        let sitemaps = parse_sitemap_index(xml).unwrap();
+        let sitemaps = parse_index(xml);
         assert_eq!(sitemaps.len(), 2);
         assert_eq!(sitemaps[0], "https://example.com/posts-sitemap.xml");
         assert_eq!(sitemaps[1], "https://example.com/pages-sitemap.xml");
     }
+
+    fn parse_fixture(bytes: &[u8], url: &str) -> Vec<SitemapItem> {
+        let mut items = Vec::new();
+        let body = decompress_if_gzip(bytes, url).unwrap();
+        parse_sitemap(body, |item| items.push(item)).unwrap();
+        items
+    }
+
+    #[test]
+    fn test_parse_gzip_sitemap() {
+        let items = parse_fixture(
+            include_bytes!("../tests/fixtures/sitemap.xml.gz"),
+            "https://example.com/sitemap.xml.gz",
+        );
+        assert_eq!(items.len(), 2);
+        assert!(matches!(&items[0], SitemapItem::Url(e) if e.url == "https://example.com/page1"));
+        assert!(matches!(&items[1], SitemapItem::Url(e) if e.url == "https://example.com/page2"));
+
+        // Detected by magic bytes, whatever the URL looks like
+        let items = parse_fixture(include_bytes!("../tests/fixtures/sitemap.xml.gz"), "https://example.com/sitemap");
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_gzip_sitemap_index() {
+        let items = parse_fixture(
+            include_bytes!("../tests/fixtures/sitemap_index.xml.gz"),
+            "https://example.com/sitemap_index.xml.gz",
+        );
+        let children: Vec<&str> = items
+            .iter()
+            .filter_map(|item| match item {
+                SitemapItem::Sitemap(url) => Some(url.as_str()),
+                SitemapItem::Url(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            children,
+            vec!["https://example.com/posts.xml.gz", "https://example.com/pages.xml"]
+        );
+    }
+
+    #[test]
+    fn test_plain_sitemap_with_gz_extension() {
+        // Servers that apply Content-Encoding hand us the decompressed body
+        let items = parse_fixture(
+            include_bytes!("../tests/fixtures/sitemap.xml"),
+            "https://example.com/sitemap.xml.gz",
+        );
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_sitemap_cdata_entities_prefixes_and_comments() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sm:urlset xmlns:sm="http://www.sitemaps.org/schemas/sitemap/0.9"
+           xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+  <!-- <url><loc>https://example.com/commented-out</loc></url> -->
+  <sm:url>
+    <sm:loc>https://example.com/search?q=rust&amp;page=2&#38;sort=new</sm:loc>
+    <sm:lastmod>2026-03-01</sm:lastmod>
+  </sm:url>
+  <sm:url>
+    <sm:loc><![CDATA[https://example.com/a?b=1&c=2]]></sm:loc>
+    <image:image>
+      <image:loc>https://cdn.example.com/photo.jpg</image:loc>
+    </image:image>
+  </sm:url>
+</sm:urlset>"#;
+
+        let entries = parse_urls(xml);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].url, "https://example.com/search?q=rust&page=2&sort=new");
+        assert_eq!(entries[0].date, Some("2026-03-01".to_string()));
+        assert_eq!(entries[1].url, "https://example.com/a?b=1&c=2");
+        assert_eq!(entries[1].date, None);
+    }
 }
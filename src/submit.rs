@@ -1,24 +1,27 @@
 //! IndexNow submission logic
 
-/// Copyright (C) 2026 Andre Franca <andre@abf.li>
-///
-/// This program is free software: you can redistribute it and/or modify
-/// it under the terms of the GNU Affero General Public License as published by
-/// the Free Software Foundation, either version 3 of the License, or
-/// (at your option) any later version.
-///
-/// This program is distributed in the hope that it will be useful,
-/// but WITHOUT ANY WARRANTY; without even the implied warranty of
-/// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
-/// GNU Affero General Public License for more details.
-///
-/// You should have received a copy of the GNU Affero General Public License
-/// along with this program.  If not, see <https://www.gnu.org/licenses/>.
-
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::SourceHost;
+use crate::hosts::is_on_host;
+use crate::output::say;
 use colored::*;
 use reqwest::blocking::Client;
 use serde::Serialize;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Maximum URLs per bulk submission (IndexNow limit is 10,000)
 pub const MAX_BATCH_SIZE: usize = 10_000;
@@ -27,11 +30,13 @@ pub const MAX_BATCH_SIZE: usize = 10_000;
 struct BulkRequest<'a> {
     host: &'a str,
     key: &'a str,
+    #[serde(rename = "keyLocation", skip_serializing_if = "Option::is_none")]
+    key_location: Option<&'a str>,
     #[serde(rename = "urlList")]
     url_list: &'a [String],
 }
 
-/// URL with optional reason for submission (new or modified)
+/// URL with the reason for submission (new, modified, retried, removed, or forced)
 #[derive(Debug, Clone)]
 pub struct SubmitEntry {
     pub url: String,
@@ -42,6 +47,25 @@ pub struct SubmitEntry {
 pub enum SubmitReason {
     New,
     Modified { date: String },
+    /// Recorded on a previous run but never accepted by IndexNow
+    Retry,
+    /// No longer listed by the source, submitted so search engines recrawl it
+    Removed,
+    /// Submitted on request, whether or not it changed
+    Forced,
+}
+
+impl SubmitReason {
+    /// Short machine-friendly name of the reason, as stored in the database
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SubmitReason::New => "new",
+            SubmitReason::Modified { .. } => "modified",
+            SubmitReason::Retry => "retry",
+            SubmitReason::Removed => "removed",
+            SubmitReason::Forced => "forced",
+        }
+    }
 }
 
 impl std::fmt::Display for SubmitReason {
@@ -49,41 +73,213 @@ impl std::fmt::Display for SubmitReason {
         match self {
             SubmitReason::New => write!(f, "new"),
             SubmitReason::Modified { date } => write!(f, "modified on {}", date),
+            SubmitReason::Retry => write!(f, "retry"),
+            SubmitReason::Removed => write!(f, "removed"),
+            SubmitReason::Forced => write!(f, "forced"),
         }
     }
 }
 
-pub fn submit_single(api_key: &str, _host: &str, searchengine: &str, entry: &SubmitEntry) -> Result<(), Box<dyn std::error::Error>> {
-    let client = build_client()?;
+/// Error returned when an IndexNow endpoint rejects a submission or cannot be reached
+#[derive(Debug, Clone)]
+pub struct SubmitError {
+    /// HTTP status code, or `None` if no response was received
+    pub status: Option<u16>,
+    /// Delay requested by the endpoint through the `Retry-After` header
+    pub retry_after: Option<Duration>,
+    message: String,
+}
+
+impl SubmitError {
+    fn from_status(status: reqwest::StatusCode, retry_after: Option<Duration>) -> Self {
+        SubmitError {
+            status: Some(status.as_u16()),
+            retry_after,
+            message: format!("Submission failed with status {}", status),
+        }
+    }
+
+    fn from_transport(error: reqwest::Error) -> Self {
+        SubmitError {
+            status: None,
+            retry_after: None,
+            message: format!("Submission request failed: {}", error),
+        }
+    }
+
+    /// Rate limiting, server errors and network failures are worth retrying later
+    pub fn is_retryable(&self) -> bool {
+        match self.status {
+            None => true,
+            Some(status) => status == 429 || status >= 500,
+        }
+    }
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+/// Outcome of a single request to an IndexNow endpoint
+#[derive(Debug)]
+pub struct BatchReport<'a> {
+    pub endpoint: &'a str,
+    /// Host the URLs were submitted for
+    pub host: &'a str,
+    pub entries: &'a [SubmitEntry],
+    /// HTTP status code, or `None` if no response was received
+    pub status: Option<u16>,
+    pub response_body: String,
+    pub duration: Duration,
+    /// Why the batch was not accepted, if it wasn't
+    pub error: Option<&'a SubmitError>,
+}
+
+impl BatchReport<'_> {
+    /// Whether the endpoint accepted the batch (HTTP 200/202)
+    pub fn accepted(&self) -> bool {
+        matches!(self.status, Some(200..=299))
+    }
+}
+
+/// Raw result of a request, before it is turned into a `BatchReport`
+struct Attempt {
+    status: Option<u16>,
+    response_body: String,
+    duration: Duration,
+    error: Option<SubmitError>,
+}
+
+/// Send a request and collect its status, body and timing. Non-2xx responses
+/// and transport failures are returned as a `SubmitError` in the attempt.
+fn send(request: reqwest::blocking::RequestBuilder, context: &str) -> Attempt {
+    let started = Instant::now();
+
+    let response = match request.send() {
+        Ok(response) => response,
+        Err(e) => {
+            say!("  {} {} - {}", "Request failed".red().bold(), context, e);
+            return Attempt {
+                status: None,
+                response_body: String::new(),
+                duration: started.elapsed(),
+                error: Some(SubmitError::from_transport(e)),
+            };
+        }
+    };
+
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+    let response_body = response.text().unwrap_or_default();
+    let duration = started.elapsed();
+
+    print_status_response(status.as_u16(), context);
+
+    let error = if !status.is_success() && status.as_u16() != 202 {
+        Some(SubmitError::from_status(status, retry_after))
+    } else {
+        None
+    };
+
+    Attempt {
+        status: Some(status.as_u16()),
+        response_body,
+        duration,
+        error,
+    }
+}
 
-    let submit_url = format!(
+fn submit_single(client: &Client, host: &SourceHost, searchengine: &str, entry: &SubmitEntry) -> Attempt {
+    let mut submit_url = format!(
         "https://{}/indexnow?url={}&key={}",
         searchengine,
         urlencoding::encode(&entry.url),
-        api_key
+        host.api_key
     );
+    if let Some(key_location) = &host.key_location {
+        submit_url.push_str(&format!("&keyLocation={}", urlencoding::encode(key_location)));
+    }
 
     print_url_info(entry);
 
-    let response = client.get(&submit_url).send()?;
-    let status = response.status();
+    send(client.get(&submit_url), &entry.url)
+}
 
-    print_status_response(status.as_u16(), &entry.url)?;
+/// Submit URLs with one set of bulk requests per host, since IndexNow accepts
+/// URLs of a single host per request. Entries that are on none of the hosts
+/// are skipped; callers quarantine them beforehand.
+///
+/// `on_batch` is called with a report for every request as soon as it completes,
+/// so callers can record accepted batches even if a later batch fails.
+/// A host stops at its first batch that is not accepted, without holding back
+/// the other hosts; the first error is returned once every host was tried.
+pub fn submit_in_batches<F>(
+    hosts: &[SourceHost],
+    searchengine: &str,
+    entries: &[SubmitEntry],
+    mut on_batch: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(&BatchReport) -> Result<(), Box<dyn std::error::Error>>,
+{
+    let client = build_client()?;
+    let mut first_error: Option<SubmitError> = None;
 
-    if !status.is_success() && status.as_u16() != 202 {
-        return Err(format!("Submission failed with status {}", status).into());
+    let mut groups: Vec<Vec<SubmitEntry>> = vec![Vec::new(); hosts.len()];
+    for entry in entries {
+        if let Some(index) = hosts.iter().position(|h| is_on_host(&entry.url, &h.host)) {
+            groups[index].push(entry.clone());
+        }
     }
 
-    Ok(())
+    for (host, host_entries) in hosts.iter().zip(&groups) {
+        if host_entries.is_empty() {
+            continue;
+        }
+        if hosts.len() > 1 {
+            say!(
+                "  {} {} URL(s) for {}",
+                "→".blue().bold(),
+                host_entries.len(),
+                host.host.cyan()
+            );
+        }
+        if let Some(error) = submit_host(&client, host, searchengine, host_entries, &mut on_batch)? {
+            first_error.get_or_insert(error);
+        }
+    }
+
+    match first_error {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
 }
 
-/// Submit URLs in batches of up to MAX_BATCH_SIZE
-pub fn submit_in_batches(api_key: &str, host: &str, searchengine: &str, entries: &[SubmitEntry]) -> Result<(), Box<dyn std::error::Error>> {
+/// Submit the URLs of one host in batches of up to MAX_BATCH_SIZE. Returns
+/// the error of the first batch that is not accepted, if any.
+fn submit_host<F>(
+    client: &Client,
+    host: &SourceHost,
+    searchengine: &str,
+    entries: &[SubmitEntry],
+    on_batch: &mut F,
+) -> Result<Option<SubmitError>, Box<dyn std::error::Error>>
+where
+    F: FnMut(&BatchReport) -> Result<(), Box<dyn std::error::Error>>,
+{
     let total = entries.len();
-    let num_batches = (total + MAX_BATCH_SIZE - 1) / MAX_BATCH_SIZE;
+    let num_batches = total.div_ceil(MAX_BATCH_SIZE);
 
     if num_batches > 1 {
-        println!(
+        say!(
             "{} Submitting {} URLs in {} batches (max {} per batch)",
             "ℹ".cyan().bold(),
             total,
@@ -94,7 +290,7 @@ pub fn submit_in_batches(api_key: &str, host: &str, searchengine: &str, entries:
 
     for (batch_idx, chunk) in entries.chunks(MAX_BATCH_SIZE).enumerate() {
         if num_batches > 1 {
-            println!(
+            say!(
                 "\n{} Batch {}/{} ({} URLs)",
                 "→".blue().bold(),
                 batch_idx + 1,
@@ -103,65 +299,78 @@ pub fn submit_in_batches(api_key: &str, host: &str, searchengine: &str, entries:
             );
         }
 
-        if chunk.len() == 1 {
-            submit_single(api_key, host, searchengine, &chunk[0])?;
+        let attempt = if chunk.len() == 1 {
+            submit_single(client, host, searchengine, &chunk[0])
         } else {
-            submit_bulk(api_key, host, searchengine, chunk)?;
+            submit_bulk(client, host, searchengine, chunk)
+        };
+
+        on_batch(&BatchReport {
+            endpoint: searchengine,
+            host: &host.host,
+            entries: chunk,
+            status: attempt.status,
+            response_body: attempt.response_body,
+            duration: attempt.duration,
+            error: attempt.error.as_ref(),
+        })?;
+
+        if let Some(error) = attempt.error {
+            return Ok(Some(error));
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
-fn submit_bulk(api_key: &str, host: &str, searchengine: &str, entries: &[SubmitEntry]) -> Result<(), Box<dyn std::error::Error>> {
-    let client = build_client()?;
-
+fn submit_bulk(client: &Client, host: &SourceHost, searchengine: &str, entries: &[SubmitEntry]) -> Attempt {
     let submit_url = format!("https://{}/indexnow", searchengine);
 
     let urls: Vec<String> = entries.iter().map(|e| e.url.clone()).collect();
 
     let payload = BulkRequest {
-        host,
-        key: api_key,
+        host: &host.host,
+        key: &host.api_key,
+        key_location: host.key_location.as_deref(),
         url_list: &urls,
     };
 
-    println!("  {} (bulk submission of {} URLs)", "URLs:".bold(), entries.len());
+    say!("  {} (bulk submission of {} URLs)", "URLs:".bold(), entries.len());
     for entry in entries {
         print_url_info(entry);
     }
-    println!();
+    say!();
 
-    let response = client
+    let request = client
         .post(&submit_url)
         .header("Content-Type", "application/json; charset=utf-8")
-        .json(&payload)
-        .send()?;
-
-    let status = response.status();
-
-    print_status_response(status.as_u16(), "bulk submission")?;
+        .json(&payload);
 
-    if !status.is_success() && status.as_u16() != 202 {
-        return Err(format!("Submission failed with status {}", status).into());
-    }
-
-    Ok(())
+    send(request, "bulk submission")
 }
 
 fn print_url_info(entry: &SubmitEntry) {
     match &entry.reason {
         SubmitReason::New => {
-            println!("    {} {} {}", "•".green(), entry.url, "(new)".green());
+            say!("    {} {} {}", "•".green(), entry.url, "(new)".green());
         }
         SubmitReason::Modified { date } => {
-            println!(
+            say!(
                 "    {} {} {}",
                 "•".yellow(),
                 entry.url,
                 format!("(modified on {})", date).yellow()
             );
         }
+        SubmitReason::Retry => {
+            say!("    {} {} {}", "•".cyan(), entry.url, "(retry)".cyan());
+        }
+        SubmitReason::Removed => {
+            say!("    {} {} {}", "•".red(), entry.url, "(removed)".red());
+        }
+        SubmitReason::Forced => {
+            say!("    {} {} {}", "•".blue(), entry.url, "(forced)".blue());
+        }
     }
 }
 
@@ -179,112 +388,162 @@ fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
         .build()?)
 }
 
-fn print_status_response(status: u16, context: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Parse a `Retry-After` header given either as delta-seconds or as an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = parse_http_date(value)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(at.saturating_sub(now)))
+}
+
+/// Parse an IMF-fixdate such as `Wed, 21 Oct 2015 07:28:00 GMT` into a Unix timestamp
+fn parse_http_date(value: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 6 || parts[5] != "GMT" {
+        return None;
+    }
+    let day: i64 = parts[1].parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == parts[2])? as i64 + 1;
+    let year: i64 = parts[3].parse().ok()?;
+    let hms: Vec<i64> = parts[4]
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    if hms.len() != 3 {
+        return None;
+    }
+
+    // Days since the Unix epoch for a proleptic Gregorian date
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    u64::try_from(days * 86_400 + hms[0] * 3_600 + hms[1] * 60 + hms[2]).ok()
+}
+
+fn print_status_response(status: u16, context: &str) {
     match status {
         200 => {
-            println!(
+            say!(
                 "  {} {} - Submission successful.",
                 "200 OK".green().bold(),
                 context
             );
         }
         202 => {
-            println!(
+            say!(
                 "  {} {} - Accepted, URL received.",
                 "202 Accepted".green().bold(),
                 context
             );
         }
         400 => {
-            println!(
+            say!(
                 "  {} {} - Invalid format or malformed request.",
                 "400 Bad Request".red().bold(),
                 context
             );
             print_help_400();
-            return Err("Bad Request".into());
         }
         401 => {
-            println!(
+            say!(
                 "  {} {} - Invalid or missing API key.",
                 "401 Unauthorized".red().bold(),
                 context
             );
             print_help_401();
-            return Err("Unauthorized".into());
         }
         403 => {
-            println!(
+            say!(
                 "  {} {} - Key mismatch or invalid host.",
                 "403 Forbidden".red().bold(),
                 context
             );
             print_help_403();
-            return Err("Forbidden".into());
         }
         422 => {
-            println!(
+            say!(
                 "  {} {} - URLs don't belong to the host or key mismatch.",
                 "422 Unprocessable Entity".red().bold(),
                 context
             );
             print_help_422();
-            return Err("Unprocessable Entity".into());
         }
         429 => {
-            println!(
+            say!(
                 "  {} {} - Rate limit exceeded.",
                 "429 Too Many Requests".yellow().bold(),
                 context
             );
             print_help_429();
-            return Err("Rate limit exceeded".into());
         }
         _ => {
-            println!(
+            say!(
                 "  {} {} - Unexpected response.",
                 format!("{}", status).yellow().bold(),
                 context
             );
         }
     }
-
-    Ok(())
 }
 
 fn print_help_400() {
-    println!("\n{}", "How to fix:".cyan().bold());
-    println!("  1. Check that your feed URLs are valid and properly formatted.");
-    println!("  2. Ensure URLs use https:// or http:// scheme.");
-    println!("  3. Verify your host configuration matches your domain.");
+    say!("\n{}", "How to fix:".cyan().bold());
+    say!("  1. Check that your feed URLs are valid and properly formatted.");
+    say!("  2. Ensure URLs use https:// or http:// scheme.");
+    say!("  3. Verify your host configuration matches your domain.");
 }
 
 fn print_help_401() {
-    println!("\n{}", "How to fix:".cyan().bold());
-    println!("  1. Verify your API key is correct.");
-    println!("  2. Make sure the key file exists at https://yourdomain.com/{{key}}.txt");
-    println!("  3. The key file must contain only the key value, nothing else.");
-    println!("  4. Run 'ixfeed config' to update your API key.");
+    say!("\n{}", "How to fix:".cyan().bold());
+    say!("  1. Verify your API key is correct.");
+    say!("  2. Make sure the key file exists at https://yourdomain.com/{{key}}.txt (check with 'ixfeed --verify').");
+    say!("  3. The key file must contain only the key value, nothing else.");
+    say!("  4. Run 'ixfeed config' to update your API key.");
 }
 
 fn print_help_403() {
-    println!("\n{}", "How to fix:".cyan().bold());
-    println!("  1. Ensure your API key file is accessible at https://{{host}}/{{key}}.txt");
-    println!("  2. Check that the host in your config matches the URLs you're submitting.");
-    println!("  3. Verify the key file contains the exact key value (no extra whitespace).");
-    println!("  4. Run 'ixfeed list' to check your current configuration.");
+    say!("\n{}", "How to fix:".cyan().bold());
+    say!("  1. Ensure your API key file is accessible at https://{{host}}/{{key}}.txt ('ixfeed --verify' checks it).");
+    say!("  2. Check that the host in your config matches the URLs you're submitting.");
+    say!("  3. Verify the key file contains the exact key value (no extra whitespace).");
+    say!("  4. Run 'ixfeed list' to check your current configuration.");
 }
 
 fn print_help_422() {
-    println!("\n{}", "How to fix:".cyan().bold());
-    println!("  1. All URLs must belong to the same host specified in your config.");
-    println!("  2. Check that your feed/sitemap only contains URLs from your domain.");
-    println!("  3. Run 'ixfeed config' and verify the 'host' setting.");
+    say!("\n{}", "How to fix:".cyan().bold());
+    say!("  1. URLs outside the configured host are quarantined before submitting, so the key is the likely cause.");
+    say!("  2. Check that the key file at https://{{host}}/{{key}}.txt contains the configured key ('ixfeed --verify').");
+    say!("  3. Run 'ixfeed --show' and verify the 'host' setting.");
 }
 
 fn print_help_429() {
-    println!("\n{}", "How to fix:".cyan().bold());
-    println!("  1. Wait some time before retrying (usually a few minutes to hours).");
-    println!("  2. Consider submitting fewer URLs at once.");
-    println!("  3. IndexNow has rate limits - space out your submissions.");
+    say!("\n{}", "How to fix:".cyan().bold());
+    say!("  1. Rate-limited URLs are queued and retried automatically on later runs.");
+    say!("  2. Consider submitting fewer URLs at once.");
+    say!("  3. IndexNow has rate limits - space out your submissions.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT"), Some(1_445_412_480));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
 }
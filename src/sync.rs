@@ -0,0 +1,526 @@
+//! Declarative TOML source list reconciled against the sources table
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::config::{self, SourceType};
+use crate::db::{self, Source, SourceHost};
+use crate::filter::{self, Rule, RuleKind};
+use crate::hosts;
+use crate::keyfile;
+use crate::normalize::{TrailingSlash, UrlNormalization};
+use crate::output::say;
+use crate::watch;
+use colored::*;
+use dialoguer::Confirm;
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::path::Path;
+use url::Url;
+
+/// Top level of the config file: a list of `[[source]]` tables
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourcesFile {
+    #[serde(default, rename = "source")]
+    sources: Vec<SourceEntry>,
+}
+
+/// One `[[source]]` table as written in the file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourceEntry {
+    #[serde(rename = "type", default)]
+    source_type: Option<String>,
+    url: String,
+    key: String,
+    host: Option<String>,
+    /// URL of the key file if it is not at `https://{host}/{key}.txt`
+    key_location: Option<String>,
+    endpoints: Option<Vec<String>>,
+    /// Watch mode interval such as `15m` or `2h`
+    poll_interval: Option<String>,
+    #[serde(default)]
+    filters: FiltersEntry,
+    #[serde(default)]
+    normalize: NormalizeEntry,
+    /// Other hosts the source lists URLs for, each with its own key
+    #[serde(default)]
+    extra_hosts: Vec<ExtraHostEntry>,
+    /// Submission of URLs that disappear from the source; off if absent
+    removals: Option<RemovalsEntry>,
+}
+
+/// `removals = { after = "7d", probe = true }` of a source
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RemovalsEntry {
+    /// How long a URL must be missing, such as `7d`
+    after: String,
+    /// Only submit URLs that answer 404 or 410; defaults to true
+    probe: Option<bool>,
+}
+
+/// One entry of `extra_hosts = [{ host = "...", key = "...", key_location = "..." }]`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExtraHostEntry {
+    host: String,
+    key: String,
+    key_location: Option<String>,
+}
+
+/// `filters = { include = [...], exclude = [...] }` of a source
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FiltersEntry {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// A source as it should exist in the database after applying the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesiredSource {
+    pub source_type: SourceType,
+    pub url: String,
+    pub key: String,
+    pub host: String,
+    pub key_location: Option<String>,
+    pub endpoints: Vec<String>,
+    pub poll_interval: Option<i64>,
+    pub filters: Vec<Rule>,
+    pub normalization: UrlNormalization,
+    pub extra_hosts: Vec<SourceHost>,
+    pub removal_grace: Option<i64>,
+    pub removal_probe: bool,
+}
+
+/// `normalize = { trailing_slash = "remove", strip_tracking = true, sort_query = true }`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NormalizeEntry {
+    trailing_slash: Option<String>,
+    strip_tracking: Option<bool>,
+    sort_query: Option<bool>,
+}
+
+/// One step of an apply plan
+#[derive(Debug)]
+pub enum Change {
+    Add(DesiredSource),
+    /// Update a source in place, keeping its tracked URLs. Lists the changed fields.
+    Update { id: i64, desired: DesiredSource, fields: Vec<&'static str> },
+    Remove(Source),
+}
+
+/// Read and validate a sources file. URLs are normalized but not probed.
+pub fn load(path: &Path) -> Result<Vec<DesiredSource>, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse(&content).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+fn parse(content: &str) -> Result<Vec<DesiredSource>, String> {
+    let file: SourcesFile = toml::from_str(content).map_err(|e| e.message().to_string())?;
+
+    let mut desired: Vec<DesiredSource> = Vec::new();
+    for (index, entry) in file.sources.into_iter().enumerate() {
+        let at = |message: String| format!("source #{} ({}): {}", index + 1, entry.url, message);
+
+        let source_type = match &entry.source_type {
+            Some(value) => value.parse::<SourceType>().map_err(at)?,
+            None => SourceType::default(),
+        };
+        let url = config::normalize_source_url(&entry.url, source_type).map_err(at)?;
+        if desired.iter().any(|d| d.url == url) {
+            return Err(at("URL is listed more than once".to_string()));
+        }
+
+        let key = entry.key.trim();
+        keyfile::validate_key(key).map_err(at)?;
+        let host = match entry.host.as_deref().map(str::trim) {
+            Some("") => return Err(at("host must not be empty".to_string())),
+            Some(host) => host.to_string(),
+            None => Url::parse(&url)
+                .ok()
+                .and_then(|u| u.host_str().map(String::from))
+                .ok_or_else(|| at("host is required for this URL".to_string()))?,
+        };
+        let key_location = match entry.key_location.as_deref().map(str::trim) {
+            Some("") | None => None,
+            Some(location) => {
+                hosts::validate_key_location(location, &host).map_err(at)?;
+                Some(location.to_string())
+            }
+        };
+        let endpoints = match &entry.endpoints {
+            Some(list) => config::parse_endpoints(&list.join(",")),
+            None => vec![config::DEFAULT_ENDPOINT.to_string()],
+        };
+        if endpoints.is_empty() {
+            return Err(at("endpoints must list at least one host".to_string()));
+        }
+        let poll_interval = entry
+            .poll_interval
+            .as_deref()
+            .map(watch::parse_interval)
+            .transpose()
+            .map_err(at)?;
+        let rule = |kind| move |pattern: &String| Rule { kind, pattern: pattern.clone() };
+        let filters: Vec<Rule> = entry
+            .filters
+            .include
+            .iter()
+            .map(rule(RuleKind::Include))
+            .chain(entry.filters.exclude.iter().map(rule(RuleKind::Exclude)))
+            .collect();
+        for rule in &filters {
+            filter::validate_pattern(&rule.pattern).map_err(at)?;
+        }
+
+        let defaults = UrlNormalization::default();
+        let normalization = UrlNormalization {
+            trailing_slash: match &entry.normalize.trailing_slash {
+                Some(value) => value.parse::<TrailingSlash>().map_err(at)?,
+                None => defaults.trailing_slash,
+            },
+            strip_tracking: entry.normalize.strip_tracking.unwrap_or(defaults.strip_tracking),
+            sort_query: entry.normalize.sort_query.unwrap_or(defaults.sort_query),
+        };
+
+        let mut extra_hosts: Vec<SourceHost> = Vec::new();
+        for extra in &entry.extra_hosts {
+            let extra = hosts::extra_host(&extra.host, &extra.key, extra.key_location.as_deref()).map_err(at)?;
+            if extra.host.eq_ignore_ascii_case(&host) || extra_hosts.iter().any(|h| h.host == extra.host) {
+                return Err(at(format!("extra host {} is listed more than once", extra.host)));
+            }
+            extra_hosts.push(extra);
+        }
+        let removal_grace = entry
+            .removals
+            .as_ref()
+            .map(|r| watch::parse_interval(&r.after))
+            .transpose()
+            .map_err(|e| at(format!("removals: {}", e)))?;
+        let removal_probe = entry.removals.as_ref().and_then(|r| r.probe).unwrap_or(true);
+
+        desired.push(DesiredSource {
+            source_type,
+            url,
+            key: key.to_string(),
+            host,
+            key_location,
+            endpoints,
+            poll_interval,
+            filters,
+            normalization,
+            extra_hosts,
+            removal_grace,
+            removal_probe,
+        });
+    }
+
+    Ok(desired)
+}
+
+/// Compare the file against the database. Sources are matched by URL, so a
+/// source whose URL is unchanged is updated in place and keeps its history;
+/// a changed URL shows up as a removal plus an addition.
+pub fn plan(desired: &[DesiredSource], current: &[Source]) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for want in desired {
+        match current.iter().find(|s| s.source_url == want.url) {
+            None => changes.push(Change::Add(want.clone())),
+            Some(have) => {
+                let mut fields = Vec::new();
+                if SourceType::from_db(&have.source_type) != want.source_type {
+                    fields.push("type");
+                }
+                if have.api_key != want.key {
+                    fields.push("key");
+                }
+                if have.host != want.host {
+                    fields.push("host");
+                }
+                if have.key_location != want.key_location {
+                    fields.push("key_location");
+                }
+                if have.endpoints != want.endpoints {
+                    fields.push("endpoints");
+                }
+                if have.poll_interval != want.poll_interval {
+                    fields.push("poll_interval");
+                }
+                if have.filters != want.filters {
+                    fields.push("filters");
+                }
+                if have.normalization != want.normalization {
+                    fields.push("normalize");
+                }
+                if have.extra_hosts != want.extra_hosts {
+                    fields.push("extra_hosts");
+                }
+                if have.removal_grace != want.removal_grace || have.removal_probe != want.removal_probe {
+                    fields.push("removals");
+                }
+                if !fields.is_empty() {
+                    changes.push(Change::Update { id: have.id, desired: want.clone(), fields });
+                }
+            }
+        }
+    }
+
+    for have in current {
+        if !desired.iter().any(|d| d.url == have.source_url) {
+            changes.push(Change::Remove(have.clone()));
+        }
+    }
+
+    changes
+}
+
+/// Apply a sources file: print the plan, then carry it out after confirmation.
+/// With `dry_run` only the plan is shown; with `unattended` it is applied without asking.
+pub fn apply_file(db_path: &Path, path: &Path, dry_run: bool, unattended: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let desired = load(path)?;
+    let conn = db::init_db(db_path)?;
+    let current = db::get_all_sources(&conn)?;
+    let changes = plan(&desired, &current);
+
+    say!(
+        "{} Apply {}",
+        "═".repeat(40).blue().bold(),
+        path.display()
+    );
+
+    if changes.is_empty() {
+        say!(
+            "\n{} Sources already match the file ({} source(s)). Nothing to do.",
+            "✓".green().bold(),
+            desired.len()
+        );
+        return Ok(());
+    }
+
+    print_plan(&conn, &changes)?;
+
+    if dry_run {
+        say!("{} Dry run - no changes made.", "ℹ".cyan().bold());
+        return Ok(());
+    }
+
+    if !unattended
+        && !Confirm::new()
+            .with_prompt("Apply these changes?")
+            .default(false)
+            .interact()?
+    {
+        say!("{} Operation cancelled.", "ℹ".cyan().bold());
+        return Ok(());
+    }
+
+    apply_changes(&conn, &changes)?;
+    say!("{} Sources updated.", "✓".green().bold());
+    Ok(())
+}
+
+fn print_plan(conn: &Connection, changes: &[Change]) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut adds, mut updates, mut removes) = (0, 0, 0);
+
+    say!();
+    for change in changes {
+        match change {
+            Change::Add(want) => {
+                adds += 1;
+                say!(
+                    "  {} [{}] {} ({})",
+                    "+".green().bold(),
+                    want.source_type.label(),
+                    want.url,
+                    want.endpoints.join(", ").dimmed()
+                );
+            }
+            Change::Update { id, desired, fields } => {
+                updates += 1;
+                say!(
+                    "  {} ID {} [{}] {} ({} changed)",
+                    "~".yellow().bold(),
+                    id,
+                    desired.source_type.label(),
+                    desired.url,
+                    fields.join(", ")
+                );
+            }
+            Change::Remove(source) => {
+                removes += 1;
+                let tracked = db::count_urls_for_source(conn, source.id)?;
+                say!(
+                    "  {} ID {} [{}] {} ({} tracked URL(s) will be forgotten)",
+                    "-".red().bold(),
+                    source.id,
+                    SourceType::from_db(&source.source_type).label(),
+                    source.source_url,
+                    tracked
+                );
+            }
+        }
+    }
+
+    say!(
+        "\n{} Plan: {} to add, {} to change, {} to remove.\n",
+        "ℹ".cyan().bold(),
+        adds,
+        updates,
+        removes
+    );
+    Ok(())
+}
+
+/// Carry out a plan in one transaction so a failure leaves the sources untouched
+fn apply_changes(conn: &Connection, changes: &[Change]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    for change in changes {
+        match change {
+            Change::Add(want) => {
+                let id = db::add_source(&tx, want.source_type.as_str(), &want.url, &want.key, &want.host, &want.endpoints)?;
+                db::set_source_key_location(&tx, id, want.key_location.as_deref())?;
+                db::set_source_poll_interval(&tx, id, want.poll_interval)?;
+                db::set_source_filters(&tx, id, &want.filters)?;
+                db::set_source_normalization(&tx, id, &want.normalization)?;
+                db::set_source_extra_hosts(&tx, id, &want.extra_hosts)?;
+                db::set_source_removals(&tx, id, want.removal_grace, want.removal_probe)?;
+            }
+            Change::Update { id, desired, .. } => {
+                db::update_source(&tx, *id, desired.source_type.as_str(), &desired.url, &desired.key, &desired.host, &desired.endpoints)?;
+                db::set_source_key_location(&tx, *id, desired.key_location.as_deref())?;
+                db::set_source_poll_interval(&tx, *id, desired.poll_interval)?;
+                db::set_source_filters(&tx, *id, &desired.filters)?;
+                db::set_source_normalization(&tx, *id, &desired.normalization)?;
+                db::set_source_extra_hosts(&tx, *id, &desired.extra_hosts)?;
+                db::set_source_removals(&tx, *id, desired.removal_grace, desired.removal_probe)?;
+            }
+            Change::Remove(source) => {
+                db::remove_source(&tx, source.id)?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: i64, url: &str, key: &str) -> Source {
+        Source {
+            id,
+            source_type: "sitemap".to_string(),
+            source_url: url.to_string(),
+            api_key: key.to_string(),
+            host: "example.com".to_string(),
+            key_location: None,
+            endpoints: vec!["api.indexnow.org".to_string()],
+            first_run_completed: true,
+            poll_interval: None,
+            filters: Vec::new(),
+            normalization: UrlNormalization::default(),
+            extra_hosts: Vec::new(),
+            removal_grace: None,
+            removal_probe: true,
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let desired = parse(
+            r#"
+            [[source]]
+            type = "sitemap"
+            url = "https://example.com/sitemap.xml"
+            key = "abc12345"
+
+            [[source]]
+            type = "sitemap"
+            url = "https://example.com/news.xml"
+            key = "new-key-1"
+            endpoints = ["https://www.bing.com/", "yandex.com"]
+            filters = { exclude = ["/tag/*"] }
+
+            [[source]]
+            url = "https://example.com/feed.xml"
+            key = "abc12345"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(desired[1].endpoints, vec!["www.bing.com", "yandex.com"]);
+        assert_eq!(desired[2].source_type, SourceType::Feed);
+        assert_eq!(desired[2].host, "example.com");
+
+        let current = vec![
+            source(1, "https://example.com/sitemap.xml", "abc12345"),
+            source(2, "https://example.com/news.xml", "old-key-1"),
+            source(3, "https://example.com/old.xml", "abc12345"),
+        ];
+        let changes = plan(&desired, &current);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(&changes[0], Change::Update { id: 2, fields, .. } if *fields == vec!["key", "endpoints", "filters"]));
+        assert!(matches!(&changes[1], Change::Add(d) if d.url == "https://example.com/feed.xml"));
+        assert!(matches!(&changes[2], Change::Remove(s) if s.id == 3));
+    }
+
+    #[test]
+    fn test_apply_keeps_history_of_updated_sources() {
+        let conn = db::init_db(Path::new(db::IN_MEMORY)).unwrap();
+        let endpoints = vec!["api.indexnow.org".to_string()];
+        let kept = db::add_source(&conn, "sitemap", "https://example.com/sitemap.xml", "old-key-1", "example.com", &endpoints).unwrap();
+        let dropped = db::add_source(&conn, "feed", "https://example.com/feed.xml", "old-key-1", "example.com", &endpoints).unwrap();
+        for id in [kept, dropped] {
+            db::record_url_for_source(&conn, id, "https://example.com/a", None, db::UrlStatus::Submitted).unwrap();
+        }
+
+        let desired = parse("[[source]]\ntype = \"sitemap\"\nurl = \"https://example.com/sitemap.xml\"\nkey = \"new-key-1\"\n").unwrap();
+        let changes = plan(&desired, &db::get_all_sources(&conn).unwrap());
+        apply_changes(&conn, &changes).unwrap();
+
+        let sources = db::get_all_sources(&conn).unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].api_key, "new-key-1");
+        assert_eq!(db::count_urls_for_source(&conn, kept).unwrap(), 1);
+        assert_eq!(db::count_urls_for_source(&conn, dropped).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_parse_rejects_duplicates_and_unknown_fields() {
+        let duplicate = r#"
+            [[source]]
+            url = "https://example.com/feed.xml"
+            key = "abc12345"
+            [[source]]
+            url = "https://example.com/feed.xml"
+            key = "abc12345"
+        "#;
+        assert!(parse(duplicate).unwrap_err().contains("more than once"));
+        assert!(parse("[[source]]\nurl = \"https://example.com/\"\nkey = \"k\"\n").unwrap_err().contains("8 to 128 characters"));
+        assert!(parse("[[source]]\nurl = \"https://example.com/\"\nkey = \"key12345\"\napi_key = \"key12345\"\n").is_err());
+        let off_host = "[[source]]\nurl = \"https://example.com/\"\nkey = \"key12345\"\nkey_location = \"https://cdn.example.net/k.txt\"\n";
+        assert!(parse(off_host).unwrap_err().contains("not on example.com"));
+        let removals = "[[source]]\nurl = \"https://example.com/\"\nkey = \"key12345\"\nremovals = { after = \"7d\" }\n";
+        let parsed = parse(removals).unwrap();
+        assert_eq!((parsed[0].removal_grace, parsed[0].removal_probe), (Some(7 * 86_400), true));
+    }
+}
@@ -0,0 +1,253 @@
+//! Resident watch mode polling each source on its own interval
+
+// Copyright (C) 2026 Andre Franca <andre@abf.li>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, Source};
+use crate::output::{self, say};
+use crate::queue;
+use colored::*;
+use rusqlite::Connection;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Poll interval of sources that don't set their own (1 hour)
+pub const DEFAULT_POLL_INTERVAL: i64 = 3600;
+
+/// Shortest accepted poll interval
+pub const MIN_POLL_INTERVAL: i64 = 60;
+
+/// Longest accepted interval (one year)
+const MAX_INTERVAL: i64 = 365 * 86_400;
+
+/// Polls are spread by up to this fraction of the interval either way
+const JITTER: f64 = 0.1;
+
+/// How often the source list is re-read to pick up added and removed sources
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Granularity at which sleeping threads notice a shutdown request
+const TICK: Duration = Duration::from_millis(250);
+
+/// Processes one source; errors are reported and the source is polled again later
+pub type ProcessFn = dyn Fn(&Connection, &Source) -> Result<(), Box<dyn std::error::Error>> + Send + Sync;
+
+/// Parse a poll interval such as `90s`, `15m`, `2h`, `1d` or a number of seconds
+pub fn parse_interval(value: &str) -> Result<i64, String> {
+    let value = value.trim().to_ascii_lowercase();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value.as_str(), "s"),
+    };
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("expected an interval like 90s, 15m, 2h or 1d, got '{}'", value))?;
+    let unit_seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        other => return Err(format!("unknown interval unit '{}', use s, m, h or d", other)),
+    };
+    let seconds = number
+        .checked_mul(unit_seconds)
+        .filter(|s| *s <= MAX_INTERVAL)
+        .ok_or_else(|| format!("interval too large, the maximum is {} days", MAX_INTERVAL / 86_400))?;
+    if seconds < MIN_POLL_INTERVAL {
+        return Err(format!("interval must be at least {} seconds", MIN_POLL_INTERVAL));
+    }
+    Ok(seconds)
+}
+
+/// Poll every source (or the given IDs) on its own interval until SIGINT/SIGTERM.
+///
+/// Each source runs on its own thread with its own database connection, so a
+/// slow sitemap does not delay the others. On shutdown, polls in progress are
+/// allowed to finish; a second signal exits immediately.
+pub fn run(
+    db_path: &Path,
+    entry_filter: Option<&[i64]>,
+    default_interval: i64,
+    process: Arc<ProcessFn>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = Arc::clone(&stop);
+        ctrlc::set_handler(move || {
+            if stop.swap(true, Ordering::SeqCst) {
+                output::finish();
+                std::process::exit(130);
+            }
+            say!(
+                "\n{} Stopping after the polls in progress finish (signal again to exit now)...",
+                "ℹ".cyan().bold()
+            );
+        })?;
+    }
+
+    let conn = db::init_db(db_path)?;
+    say!(
+        "{} Watching sources (default interval {}). Press Ctrl+C to stop.\n",
+        "ℹ".cyan().bold(),
+        queue::format_delay(default_interval)
+    );
+
+    let mut workers: HashMap<i64, JoinHandle<()>> = HashMap::new();
+    loop {
+        workers.retain(|_, handle| !handle.is_finished());
+
+        let sources: Vec<Source> = db::get_all_sources(&conn)?
+            .into_iter()
+            .filter(|s| entry_filter.is_none_or(|ids| ids.contains(&s.id)))
+            .collect();
+        if sources.is_empty() && workers.is_empty() {
+            return Err("No sources to watch. Run 'ixfeed --add' to add a source.".into());
+        }
+
+        for source in sources {
+            if workers.contains_key(&source.id) {
+                continue;
+            }
+            let worker = Worker {
+                db_path: db_path.to_path_buf(),
+                source_id: source.id,
+                default_interval,
+                process: Arc::clone(&process),
+                stop: Arc::clone(&stop),
+            };
+            workers.insert(source.id, thread::spawn(move || worker.run()));
+        }
+
+        if !sleep_unless_stopped(RELOAD_INTERVAL, &stop) {
+            break;
+        }
+    }
+
+    for (_, handle) in workers {
+        let _ = handle.join();
+    }
+    say!("{} Watch mode stopped.", "✓".green().bold());
+    Ok(())
+}
+
+/// Polling loop of a single source
+struct Worker {
+    db_path: PathBuf,
+    source_id: i64,
+    default_interval: i64,
+    process: Arc<ProcessFn>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Worker {
+    fn run(self) {
+        let conn = match db::init_db(&self.db_path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                say!("{} [ID {}] {}", "✗".red().bold(), self.source_id, e);
+                return;
+            }
+        };
+
+        while !self.stop.load(Ordering::SeqCst) {
+            // Re-read the source so edits made while watching take effect
+            let source = match db::get_all_sources(&conn) {
+                Ok(sources) => sources.into_iter().find(|s| s.id == self.source_id),
+                Err(e) => {
+                    say!("{} [ID {}] {}", "✗".red().bold(), self.source_id, e);
+                    return;
+                }
+            };
+            let Some(source) = source else {
+                say!("{} [ID {}] Source removed, no longer watching it.", "ℹ".cyan().bold(), self.source_id);
+                return;
+            };
+
+            if source.api_key.is_empty() || source.host.is_empty() {
+                say!(
+                    "{} [ID {}] Missing API key or host. Run '{} --config' to configure.",
+                    "⚠".yellow().bold(),
+                    source.id,
+                    env!("CARGO_PKG_NAME")
+                );
+            } else if let Err(e) = (self.process)(&conn, &source) {
+                say!("{} [ID {}] {}", "✗".red().bold(), source.id, e);
+            }
+
+            let wait = jittered(source.poll_interval.unwrap_or(self.default_interval));
+            say!(
+                "{} [ID {}] Next poll in {}.\n",
+                "↻".cyan().bold(),
+                source.id,
+                queue::format_delay(wait.as_secs() as i64)
+            );
+            if !sleep_unless_stopped(wait, &self.stop) {
+                return;
+            }
+        }
+    }
+}
+
+/// Spread an interval by a random amount of up to ±JITTER so sources with the
+/// same interval don't all poll at once
+fn jittered(interval: i64) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let factor = 1.0 + JITTER * ((random as f64 / u64::MAX as f64) * 2.0 - 1.0);
+    Duration::from_secs_f64(interval as f64 * factor)
+}
+
+/// Sleep for `duration`, waking early if a stop was requested. Returns `false` if stopped.
+fn sleep_unless_stopped(duration: Duration, stop: &AtomicBool) -> bool {
+    let deadline = Instant::now() + duration;
+    while !stop.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep(TICK.min(deadline - now));
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("90s"), Ok(90));
+        assert_eq!(parse_interval("15m"), Ok(900));
+        assert_eq!(parse_interval("2h"), Ok(7200));
+        assert_eq!(parse_interval("1d"), Ok(86_400));
+        assert_eq!(parse_interval("600"), Ok(600));
+        assert!(parse_interval("30s").is_err());
+        assert!(parse_interval("5w").is_err());
+        assert!(parse_interval("soon").is_err());
+        assert_eq!(parse_interval("365d"), Ok(MAX_INTERVAL));
+        assert!(parse_interval("366d").unwrap_err().contains("too large"));
+        assert!(parse_interval("999999999999999d").unwrap_err().contains("too large"));
+
+        for _ in 0..20 {
+            let wait = jittered(1000).as_secs_f64();
+            assert!((900.0..=1100.0).contains(&wait));
+        }
+    }
+}